@@ -0,0 +1,130 @@
+//! Compares concurrent cache reads through the shared `RedisUrlCache` against the
+//! previous adapter, which queued every call behind a `Mutex` and looked codes up
+//! with two sequential `GET`s.
+//!
+//! Needs a Redis server on `localhost:6379`; database 15 is used as scratch space.
+//!
+//! ```sh
+//! cargo bench -p wee-core --bench redis_url_cache
+//! ```
+
+use std::{num::NonZeroU64, sync::Arc};
+
+use chrono::Utc;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use futures_util::future::join_all;
+use map_macro::hash_map;
+use redis::AsyncCommands;
+use tokio::{runtime::Runtime, sync::Mutex};
+use wee_core::{
+    domain::entities::{Entity, url::Url},
+    outbound::redis::{RedisConfig, connection::RedisConnection, url_cache::RedisUrlCache},
+};
+
+/// How many redirects are resolved at once
+const CONCURRENCY: [usize; 3] = [1, 16, 128];
+
+/// Looked up as an alias, so the old adapter pays for both round trips.
+const ALIAS: &str = "bench-alias";
+
+/// The previous adapter: one locked connection and a `GET` per key.
+struct LockedCache {
+    cache: RedisUrlCache,
+    conn: Arc<Mutex<RedisConnection>>,
+}
+
+impl LockedCache {
+    async fn get_by_code(&self, code: &str) -> Option<Url> {
+        let short: Option<String> = self
+            .conn
+            .lock()
+            .await
+            .get(self.cache.keys.short(code))
+            .await
+            .unwrap();
+        let value = match short {
+            Some(value) => Some(value),
+            None => self
+                .conn
+                .lock()
+                .await
+                .get(self.cache.keys.alias(code))
+                .await
+                .unwrap(),
+        };
+
+        value.map(|json| Url::from_json(&json).unwrap())
+    }
+}
+
+async fn setup() -> (RedisUrlCache, LockedCache) {
+    let config = RedisConfig::builder()
+        .host("localhost")
+        .port(6379)
+        .dbs(hash_map! {
+            "url_cache".to_string() => 15,
+        })
+        .namespace("wee-bench")
+        .max_ttl_secs(NonZeroU64::new(3_600).unwrap())
+        .build();
+    let cache = RedisUrlCache::new(config).await.unwrap();
+    let url = Url::builder()
+        .long("https://example.com/bench".to_string())
+        .short("bench".to_string())
+        .alias(Some(ALIAS.to_string()))
+        .expiration_date(None)
+        .user_id("bench_user".to_string())
+        .created_at(Utc::now().naive_utc())
+        .updated_at(Utc::now().naive_utc())
+        .build();
+    cache.set(&url).await.unwrap();
+
+    let locked = LockedCache {
+        cache: cache.clone(),
+        conn: Arc::new(Mutex::new(cache.conn.clone())),
+    };
+
+    (cache, locked)
+}
+
+fn bench_get_by_code(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let (cache, locked) = runtime.block_on(setup());
+    let cache = Arc::new(cache);
+    let locked = Arc::new(locked);
+
+    let mut group = c.benchmark_group("get_by_code");
+    for concurrency in CONCURRENCY {
+        group.throughput(Throughput::Elements(concurrency as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("mutex_sequential_get", concurrency),
+            &concurrency,
+            |b, &concurrency| {
+                b.to_async(&runtime).iter(|| {
+                    join_all((0..concurrency).map(|_| {
+                        let locked = locked.clone();
+                        tokio::spawn(async move { locked.get_by_code(ALIAS).await })
+                    }))
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("cloned_mget", concurrency),
+            &concurrency,
+            |b, &concurrency| {
+                b.to_async(&runtime).iter(|| {
+                    join_all((0..concurrency).map(|_| {
+                        let cache = cache.clone();
+                        tokio::spawn(async move { cache.get_by_code(ALIAS).await.unwrap() })
+                    }))
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_get_by_code);
+criterion_main!(benches);
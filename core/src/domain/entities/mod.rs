@@ -1,4 +1,7 @@
-use serde::{Deserialize, Serialize};
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{self, Visitor},
+};
 
 pub mod url;
 
@@ -10,4 +13,43 @@ pub trait Entity: Serialize + for<'a> Deserialize<'a> {
     fn from_json(json: &str) -> Result<Self, anyhow::Error> {
         serde_json::from_str(json).map_err(anyhow::Error::from)
     }
+
+    /// The field names as they are serialized, i.e. after `rename`/`rename_all` are applied.
+    fn field_names() -> &'static [&'static str] {
+        let mut fields = None;
+        let _ = Self::deserialize(FieldNamesDeserializer {
+            fields: &mut fields,
+        });
+
+        fields.unwrap_or_default()
+    }
+}
+
+/// A deserializer that records the field list serde hands to `deserialize_struct`, then bails out.
+struct FieldNamesDeserializer<'a> {
+    fields: &'a mut Option<&'static [&'static str]>,
+}
+
+impl<'de> Deserializer<'de> for FieldNamesDeserializer<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("only structs are supported"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.fields = Some(fields);
+        Err(de::Error::custom("field names collected"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
 }
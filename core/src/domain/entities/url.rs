@@ -1,4 +1,4 @@
-use chrono::{NaiveDate, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 
 use super::Entity;
 
@@ -9,8 +9,13 @@ pub struct Url {
     pub short: String,
     #[builder(required, into)]
     pub alias: Option<String>,
+    /// The instant the link stops working, stored as `YYYY-MM-DDTHH:MM:SSZ`
     #[builder(required, into)]
-    pub expiration_date: Option<NaiveDate>,
+    #[serde(with = "expiration_date")]
+    pub expiration_date: Option<DateTime<Utc>>,
+    /// Where to send visitors once the link has expired, instead of answering 410 Gone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_url: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub user_id: String,
@@ -18,8 +23,52 @@ pub struct Url {
 
 impl Url {
     pub fn expired(&self) -> bool {
-        self.expiration_date.is_some() && self.expiration_date.unwrap() < Utc::now().date_naive()
+        self.expiration_date
+            .is_some_and(|expiration_date| expiration_date <= Utc::now())
+    }
+
+    /// Formats an expiration so that stored values sort chronologically as strings.
+    pub fn format_expiration_date(expiration_date: &DateTime<Utc>) -> String {
+        expiration_date.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    }
+
+    /// Parses an RFC 3339 timestamp, or a legacy `YYYY-MM-DD` date.
+    ///
+    /// A date-only link used to work through the whole day, so it expires at the
+    /// start of the following day (UTC).
+    pub fn parse_expiration_date(value: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
+        match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            Ok(date) => Ok((date + chrono::Days::new(1))
+                .and_time(chrono::NaiveTime::MIN)
+                .and_utc()),
+            Err(_) => DateTime::parse_from_rfc3339(value).map(|date| date.to_utc()),
+        }
     }
 }
 
 impl Entity for Url {}
+
+mod expiration_date {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    use super::Url;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_str(&Url::format_expiration_date(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| Url::parse_expiration_date(&value).map_err(de::Error::custom))
+            .transpose()
+    }
+}
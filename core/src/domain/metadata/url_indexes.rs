@@ -1,9 +1,30 @@
+use crate::domain::entities::{Entity, url::Url};
+
+#[derive(Debug, thiserror::Error)]
+pub enum UrlIndexesError {
+    #[error("Index key `{key}` is not a serialized field of Url, expected one of {fields:?}")]
+    UnknownKey {
+        key: String,
+        fields: &'static [&'static str],
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialFilter {
+    /// Every listed field exists and holds a string
+    /// For MongoDB, this maps to { field: { $type: "string" } }
+    /// For SQL databases, this maps to WHERE column IS NOT NULL
+    IsString(Vec<String>),
+}
+
 nest! {
     #[derive(Debug, Clone, PartialEq, Eq, Builder, Serialize, Deserialize)]*
     pub struct UrlIndexes {
         pub values: Vec<
             pub struct UrlIndex {
                 /// The fields to index on, e.g. ["long", "userId"] for a compound index
+                /// These are the serialized field names of `Url`, see `UrlIndexes::validate`
                 /// This works for both SQL columns and NoSQL fields
                 pub keys: Vec<String>,
                 /// Whether this index enforces uniqueness constraints
@@ -12,6 +33,11 @@ nest! {
                 /// Whether this index skips null/missing values
                 /// For SQL databases, this maps to WHERE column IS NOT NULL
                 pub is_sparse: bool,
+                /// Only entries matching this filter are indexed, e.g. so that many URLs
+                /// without an alias don't collide on a unique alias index
+                #[builder(into)]
+                #[serde(default)]
+                pub partial_filter: Option<PartialFilter>,
             },
         >,
     }
@@ -22,7 +48,7 @@ impl Default for UrlIndexes {
         Self {
             values: vec![
                 UrlIndex::builder()
-                    .keys(vec!["user_id", "long"])
+                    .keys(vec!["userId", "long"])
                     .is_unique(true)
                     .is_sparse(false)
                     .build(),
@@ -35,6 +61,7 @@ impl Default for UrlIndexes {
                     .keys(vec!["alias"])
                     .is_unique(true)
                     .is_sparse(false)
+                    .partial_filter(PartialFilter::IsString(vec!["alias".to_string()]))
                     .build(),
             ],
         }
@@ -45,4 +72,33 @@ impl UrlIndexes {
     pub fn new(values: Vec<UrlIndex>) -> Self {
         Self { values }
     }
+
+    /// Checks every index key against the serialized field names of `Url`, so a renamed
+    /// field cannot silently turn an index into one over a field that is never written.
+    pub fn validate(&self) -> Result<(), UrlIndexesError> {
+        let fields = Url::field_names();
+
+        for key in self.values.iter().flat_map(|index| index.fields()) {
+            if !fields.contains(&key.as_str()) {
+                return Err(UrlIndexesError::UnknownKey {
+                    key: key.clone(),
+                    fields,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl UrlIndex {
+    /// Every field the index refers to, in its keys or its partial filter.
+    pub fn fields(&self) -> impl Iterator<Item = &String> {
+        let filter_fields = match self.partial_filter.as_ref() {
+            Some(PartialFilter::IsString(fields)) => fields.as_slice(),
+            None => &[],
+        };
+
+        self.keys.iter().chain(filter_fields)
+    }
 }
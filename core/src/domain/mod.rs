@@ -1,3 +1,4 @@
 pub mod entities;
 pub mod metadata;
 pub mod repos;
+pub mod short_code;
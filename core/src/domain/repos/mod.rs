@@ -1 +1,3 @@
+pub mod page;
+pub mod url_query;
 pub mod url_repo;
@@ -0,0 +1,49 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+
+use super::url_repo::ListUrlsError;
+
+/// A cursor-paginated request. `after` is the opaque `next_cursor` of the previous page.
+#[derive(Debug, Clone, PartialEq, Eq, Builder)]
+pub struct PageRequest {
+    #[builder(default = 20)]
+    pub limit: u32,
+    #[builder(into)]
+    pub after: Option<String>,
+}
+
+impl PageRequest {
+    /// Checks the request and decodes `after` into the key of the previous page's last
+    /// item.
+    ///
+    /// A limit of 0 is rejected, since its empty page would look like the last one.
+    pub fn start_after(&self) -> Result<Option<String>, ListUrlsError> {
+        if self.limit == 0 {
+            return Err(ListUrlsError::InvalidLimit(self.limit));
+        }
+
+        self.after
+            .as_deref()
+            .map(|cursor| {
+                URL_SAFE_NO_PAD
+                    .decode(cursor)
+                    .ok()
+                    .and_then(|key| String::from_utf8(key).ok())
+                    .filter(|key| !key.is_empty())
+                    .ok_or_else(|| ListUrlsError::InvalidCursor(cursor.to_string()))
+            })
+            .transpose()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Builder, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// `None` when this is the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// Encodes the key of a page's last item as the page's opaque `next_cursor`.
+pub fn encode_cursor(key: &str) -> String {
+    URL_SAFE_NO_PAD.encode(key)
+}
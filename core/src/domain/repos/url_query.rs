@@ -0,0 +1,32 @@
+use crate::domain::entities::url::Url;
+
+/// Storage-agnostic lookups supported by `UrlRepo::find`.
+///
+/// Each adapter translates a query into its own language (a BSON filter, a SQL
+/// `WHERE` clause, ...); `matches` is the reference semantics they must agree with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlQuery {
+    ByShort(String),
+    ByAlias(String),
+    /// Matches a code that is either the generated short code or a custom alias.
+    ByShortOrAlias(String),
+    ByUserAndLong {
+        user_id: String,
+        long: String,
+    },
+}
+
+impl UrlQuery {
+    pub fn matches(&self, url: &Url) -> bool {
+        match self {
+            UrlQuery::ByShort(short) => url.short == *short,
+            UrlQuery::ByAlias(alias) => url.alias.as_ref() == Some(alias),
+            UrlQuery::ByShortOrAlias(code) => {
+                url.short == *code || url.alias.as_ref() == Some(code)
+            }
+            UrlQuery::ByUserAndLong { user_id, long } => {
+                url.user_id == *user_id && url.long == *long
+            }
+        }
+    }
+}
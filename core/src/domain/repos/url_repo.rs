@@ -1,9 +1,15 @@
 use std::future::Future;
 
-use mongodb::bson::Document;
+use chrono::{DateTime, Utc};
+use futures_util::stream::BoxStream;
 
 use crate::domain::entities::url::Url;
 
+use super::{
+    page::{Page, PageRequest},
+    url_query::UrlQuery,
+};
+
 nest! {
     #[derive(Debug, thiserror::Error)]*
     pub enum UrlRepoError {
@@ -37,6 +43,33 @@ nest! {
             #[error("URL not found: {0}")]
             NotFound(String),
         }),
+        #[error("Delete URL error: {0}")]
+        Delete(#[from] pub enum DeleteUrlError {
+            #[error("Client Error: {0}")]
+            ClientError(anyhow::Error),
+
+            #[error("URL not found: {0}")]
+            NotFound(String),
+        }),
+        #[error("List URLs error: {0}")]
+        List(#[from] pub enum ListUrlsError {
+            #[error("Invalid cursor: {0}")]
+            InvalidCursor(String),
+
+            #[error("Invalid limit: {0}, a page holds at least one URL")]
+            InvalidLimit(u32),
+
+            #[error("Client Error: {0}")]
+            ClientError(anyhow::Error),
+
+            #[error("Internal Error: {0}")]
+            InternalError(anyhow::Error),
+        }),
+        #[error("Count URLs error: {0}")]
+        Count(#[from] pub enum CountUrlsError {
+            #[error("Client Error: {0}")]
+            ClientError(anyhow::Error),
+        }),
     }
 }
 
@@ -51,7 +84,39 @@ pub trait UrlRepo: Send + Sync {
     fn replace_if_exists(&self, _url: Url)
     -> impl Future<Output = Result<(), UrlRepoError>> + Send;
 
-    fn find<T>(&self, query: T) -> impl Future<Output = Result<Option<Url>, UrlRepoError>> + Send
-    where
-        T: Into<Document> + Send + Sync;
+    fn find(
+        &self,
+        query: UrlQuery,
+    ) -> impl Future<Output = Result<Option<Url>, UrlRepoError>> + Send;
+
+    /// Deletes the URL with the given short code and returns it.
+    fn delete(&self, short: &str) -> impl Future<Output = Result<Url, UrlRepoError>> + Send;
+
+    /// Lists a user's URLs ordered by short code.
+    fn list_by_user(
+        &self,
+        user_id: &str,
+        page: PageRequest,
+    ) -> impl Future<Output = Result<Page<Url>, UrlRepoError>> + Send;
+
+    fn count(&self) -> impl Future<Output = Result<u64, UrlRepoError>> + Send;
+
+    fn count_by_user(
+        &self,
+        user_id: &str,
+    ) -> impl Future<Output = Result<u64, UrlRepoError>> + Send;
+
+    /// Streams every stored URL, fetching `batch_size` at a time.
+    fn scan(
+        &self,
+        batch_size: u32,
+    ) -> impl Future<Output = Result<BoxStream<'static, Result<Url, UrlRepoError>>, UrlRepoError>> + Send;
+
+    /// Deletes up to `limit` URLs that expired at or before `now` and returns them,
+    /// so callers can evict them from caches.
+    fn delete_expired(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> impl Future<Output = Result<Vec<Url>, UrlRepoError>> + Send;
 }
@@ -0,0 +1,453 @@
+use std::{collections::HashSet, sync::LazyLock};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// IDs below `1 << MIN_BITS` share one permutation domain, so their codes stay as
+/// short as the alphabet allows for a 32-bit number.
+const MIN_BITS: u32 = 32;
+const ROUNDS: u64 = 4;
+/// How many characters a hashed code starts with, before `min_length` and collisions
+/// lengthen it
+const HASHED_LENGTH: usize = 8;
+
+pub const DEFAULT_ALPHABET: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Characters people confuse when reading a code, used to pick the likely intended
+/// code out of several that pass the check.
+const LOOK_ALIKES: &[&str] = &["0Oo", "1IiLl", "2Zz", "5Ss", "6G", "8B", "9gq", "UuVv"];
+
+/// Joins the parts of a `CodeStyle::Words` code; the alphabet can't contain it, so
+/// compact and word codes never collide.
+pub const WORD_SEPARATOR: char = '-';
+
+/// Sorted word lists, so a word's index can be found with a binary search
+pub static ADJECTIVES: LazyLock<Vec<&str>> =
+    LazyLock::new(|| include_str!("short_code/adjectives.txt").lines().collect());
+pub static NOUNS: LazyLock<Vec<&str>> =
+    LazyLock::new(|| include_str!("short_code/nouns.txt").lines().collect());
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeStyle {
+    /// Written in the configured alphabet, e.g. `aZ3k`
+    #[default]
+    Compact,
+    /// An adjective, a noun and a number, e.g. `brave-tiger-42`, for links that are
+    /// read aloud or printed
+    Words,
+    /// Derived from the long URL and user instead of an ID, so the same URL gets the
+    /// same code in every environment sharing the key
+    Hashed,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShortCodeError {
+    #[error("The alphabet needs at least 2 characters, got {0:?}")]
+    AlphabetTooShort(String),
+
+    #[error("The alphabet repeats {0:?}")]
+    DuplicateCharacter(char),
+
+    #[error("The alphabet can't contain {0:?}, it separates the words of word codes")]
+    ReservedCharacter(char),
+
+    #[error("The minimum length must be at least 1")]
+    InvalidMinLength,
+}
+
+nest! {
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]*
+    pub struct ShortCodeConfig {
+        /// Secret key of the permutation and of hashed codes. Changing it changes every
+        /// code generated afterwards, so it has to stay the same for the lifetime of a
+        /// deployment.
+        pub key: u64,
+        /// The characters codes are written with, from digit 0 upwards
+        #[builder(into, default = DEFAULT_ALPHABET)]
+        pub alphabet: String,
+        /// Shorter codes are left-padded with the first character of the alphabet
+        #[builder(default = 1)]
+        pub min_length: usize,
+        /// Appends a Luhn mod N check character after the padded code, which catches
+        /// every single mistyped character
+        #[builder(default = false)]
+        pub check_character: bool,
+        /// Aliases must contain a character outside the alphabet, so that a code the
+        /// encoder can't produce is never looked up as a short code, and not at all
+        /// unless it can be an alias. Links created before the alphabet, `min_length` or
+        /// `check_character` changed, and aliases made only of alphabet characters,
+        /// become unreachable, so only turn it on for a new deployment.
+        #[builder(default = false)]
+        #[serde(default)]
+        pub strict: bool,
+    }
+}
+
+/// Turns sequential IDs into non-guessable short codes and back.
+///
+/// IDs are scrambled with a keyed Feistel permutation, then written in the
+/// configured alphabet. To keep codes short, the permutation never moves an ID out
+/// of its size class: IDs below 2^32 map onto IDs below 2^32, and larger IDs onto
+/// IDs with the same bit length rounded up to an even number, cycle-walking until
+/// they land back in their class. This keeps the mapping a bijection, so codes stay
+/// unique.
+#[derive(Debug, Clone)]
+pub struct ShortCodeEncoder {
+    pub config: ShortCodeConfig,
+    alphabet: Vec<char>,
+}
+
+impl ShortCodeEncoder {
+    pub fn new(config: ShortCodeConfig) -> Result<Self, ShortCodeError> {
+        let alphabet = config.alphabet.chars().collect::<Vec<_>>();
+        if alphabet.len() < 2 {
+            return Err(ShortCodeError::AlphabetTooShort(config.alphabet));
+        }
+
+        let mut seen = HashSet::new();
+        if let Some(&duplicate) = alphabet.iter().find(|&&c| !seen.insert(c)) {
+            return Err(ShortCodeError::DuplicateCharacter(duplicate));
+        }
+
+        if alphabet.contains(&WORD_SEPARATOR) {
+            return Err(ShortCodeError::ReservedCharacter(WORD_SEPARATOR));
+        }
+
+        if config.min_length == 0 {
+            return Err(ShortCodeError::InvalidMinLength);
+        }
+
+        Ok(ShortCodeEncoder { config, alphabet })
+    }
+
+    pub fn encode(&self, id: u64) -> String {
+        let base = self.alphabet.len() as u64;
+        let mut value = self.scramble(id);
+
+        let mut digits = Vec::new();
+        while value > 0 || digits.len() < self.config.min_length {
+            digits.push((value % base) as usize);
+            value /= base;
+        }
+        digits.reverse();
+
+        if self.config.check_character {
+            digits.push(self.check_digit(&digits));
+        }
+
+        digits
+            .into_iter()
+            .map(|digit| self.alphabet[digit])
+            .collect()
+    }
+
+    /// Writes the scrambled ID in mixed radix: its lowest digit picks the adjective,
+    /// the next one the noun, and the rest is the number. Word codes carry no check
+    /// character and ignore `min_length`.
+    pub fn encode_words(&self, id: u64) -> String {
+        let (adjectives, nouns) = (ADJECTIVES.len() as u64, NOUNS.len() as u64);
+        let scrambled = self.scramble(id);
+
+        let adjective = ADJECTIVES[(scrambled % adjectives) as usize];
+        let noun = NOUNS[(scrambled / adjectives % nouns) as usize];
+        let number = scrambled / adjectives / nouns;
+
+        format!("{adjective}{WORD_SEPARATOR}{noun}{WORD_SEPARATOR}{number}")
+    }
+
+    /// Whether `code` may be a custom alias. An alias never reads as a word code, and
+    /// in `strict` mode it has a character outside the alphabet.
+    pub fn can_be_alias(&self, code: &str) -> bool {
+        self.decode_words(code).is_none()
+            && (!self.config.strict || code.chars().any(|c| !self.alphabet.contains(&c)))
+    }
+
+    /// Derives a code from a keyed HMAC-SHA256 of the normalized long URL and the user.
+    ///
+    /// The digest is written in the alphabet and cut to `HASHED_LENGTH` characters,
+    /// or `min_length` if longer, plus `attempt` more, so a collision is resolved by
+    /// retrying with the next attempt. Returns `None` once the digest runs out.
+    pub fn hashed(&self, long: &str, user_id: &str, attempt: usize) -> Option<String> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.config.key.to_be_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(normalize_url(long).as_bytes());
+        mac.update(&[0]);
+        mac.update(user_id.as_bytes());
+        let digest = mac.finalize().into_bytes();
+
+        // Written most significant digit first, so the code never starts with padding
+        let mut digits = to_digits(&digest, self.alphabet.len());
+        let length = HASHED_LENGTH.max(self.config.min_length) + attempt;
+        if length > digits.len() {
+            return None;
+        }
+        digits.truncate(length);
+
+        if self.config.check_character {
+            digits.push(self.check_digit(&digits));
+        }
+
+        Some(
+            digits
+                .into_iter()
+                .map(|digit| self.alphabet[digit])
+                .collect(),
+        )
+    }
+
+    /// Whether the encoder can have produced `code`, hashed codes included. Unlike
+    /// `decode`, this doesn't need the code to fit in an ID.
+    pub fn is_well_formed(&self, code: &str) -> bool {
+        if code.contains(WORD_SEPARATOR) {
+            self.decode_words(code).is_some()
+        } else {
+            self.compact_digits(code).is_some()
+        }
+    }
+
+    /// Returns the ID a code of either style was generated from, or `None` if the
+    /// encoder can't have produced it: it uses characters outside the alphabet, fails
+    /// the check, is shorter than `min_length`, has more padding than needed, is too
+    /// large or isn't made of known words.
+    pub fn decode(&self, code: &str) -> Option<u64> {
+        if code.contains(WORD_SEPARATOR) {
+            self.decode_words(code)
+        } else {
+            self.decode_compact(code)
+        }
+    }
+
+    fn decode_words(&self, code: &str) -> Option<u64> {
+        let (adjectives, nouns) = (ADJECTIVES.len() as u64, NOUNS.len() as u64);
+
+        let mut parts = code.split(WORD_SEPARATOR);
+        let (Some(adjective), Some(noun), Some(number), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+
+        let adjective = ADJECTIVES.binary_search(&adjective).ok()? as u64;
+        let noun = NOUNS.binary_search(&noun).ok()? as u64;
+        // Only the canonical spelling, without a sign or leading zeros
+        let number = number
+            .parse::<u64>()
+            .ok()
+            .filter(|n| n.to_string() == number)?;
+
+        let scrambled = number
+            .checked_mul(nouns)?
+            .checked_add(noun)?
+            .checked_mul(adjectives)?
+            .checked_add(adjective)?;
+
+        Some(self.unscramble(scrambled))
+    }
+
+    fn decode_compact(&self, code: &str) -> Option<u64> {
+        let base = self.alphabet.len() as u64;
+
+        let scrambled = self
+            .compact_digits(code)?
+            .into_iter()
+            .try_fold(0u64, |value, digit| {
+                value.checked_mul(base)?.checked_add(digit as u64)
+            })?;
+
+        Some(self.unscramble(scrambled))
+    }
+
+    /// The digits of a compact code without its check character, if it passes the
+    /// check and is padded the way `encode` pads.
+    fn compact_digits(&self, code: &str) -> Option<Vec<usize>> {
+        let mut digits = code
+            .chars()
+            .map(|c| self.alphabet.iter().position(|&digit| digit == c))
+            .collect::<Option<Vec<_>>>()?;
+
+        if self.config.check_character {
+            let check = digits.pop()?;
+            if check != self.check_digit(&digits) {
+                return None;
+            }
+        }
+
+        if digits.len() < self.config.min_length
+            || (digits.len() > self.config.min_length && digits[0] == 0)
+        {
+            return None;
+        }
+
+        Some(digits)
+    }
+
+    /// Guesses the code that was meant when `code` has a single wrong character.
+    ///
+    /// Needs `check_character`. Every position is tried; when several corrections pass
+    /// the check, the only one replacing a look-alike character wins, otherwise there
+    /// is no suggestion.
+    pub fn suggest(&self, code: &str) -> Option<String> {
+        if !self.config.check_character || self.is_well_formed(code) {
+            return None;
+        }
+
+        let chars = code.chars().collect::<Vec<_>>();
+        let unknown = chars
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !self.alphabet.contains(c))
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+        // A character outside the alphabet has to be the wrong one
+        let positions = match unknown.as_slice() {
+            [] => (0..chars.len()).collect(),
+            [position] => vec![*position],
+            _ => return None,
+        };
+
+        let candidates = positions
+            .into_iter()
+            .flat_map(|position| {
+                let chars = &chars;
+                self.alphabet.iter().filter_map(move |&replacement| {
+                    let mut candidate = chars.clone();
+                    candidate[position] = replacement;
+                    let candidate = candidate.into_iter().collect::<String>();
+
+                    self.is_well_formed(&candidate)
+                        .then(|| (look_alike(chars[position], replacement), candidate))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if let [(_, candidate)] = candidates.as_slice() {
+            return Some(candidate.clone());
+        }
+
+        let mut preferred = candidates.into_iter().filter(|(look_alike, _)| *look_alike);
+        match (preferred.next(), preferred.next()) {
+            (Some((_, candidate)), None) => Some(candidate),
+            _ => None,
+        }
+    }
+
+    /// The Luhn mod N check digit of `digits`. For an odd alphabet size the doubled
+    /// digit is taken mod N instead of summing its base-N digits, which keeps doubling
+    /// a bijection so that any single substitution changes the check.
+    fn check_digit(&self, digits: &[usize]) -> usize {
+        let base = self.alphabet.len();
+
+        let sum = digits.iter().rev().enumerate().fold(0, |sum, (i, &digit)| {
+            let addend = match (i.is_multiple_of(2), base.is_multiple_of(2)) {
+                (false, _) => digit,
+                (true, true) => (2 * digit) / base + (2 * digit) % base,
+                (true, false) => (2 * digit) % base,
+            };
+            (sum + addend) % base
+        });
+
+        (base - sum) % base
+    }
+
+    pub fn scramble(&self, id: u64) -> u64 {
+        let (bits, lower) = size_class(id);
+
+        let mut value = id;
+        loop {
+            value = self.permute(value, bits);
+            if value >= lower {
+                return value;
+            }
+        }
+    }
+
+    pub fn unscramble(&self, scrambled: u64) -> u64 {
+        let (bits, lower) = size_class(scrambled);
+
+        let mut value = scrambled;
+        loop {
+            value = self.inverse_permute(value, bits);
+            if value >= lower {
+                return value;
+            }
+        }
+    }
+
+    fn permute(&self, value: u64, bits: u32) -> u64 {
+        let half = bits / 2;
+        let mask = (1 << half) - 1;
+        let (mut left, mut right) = (value >> half, value & mask);
+
+        for round in 0..ROUNDS {
+            (left, right) = (right, left ^ (self.round(round, right) & mask));
+        }
+
+        (left << half) | right
+    }
+
+    fn inverse_permute(&self, value: u64, bits: u32) -> u64 {
+        let half = bits / 2;
+        let mask = (1 << half) - 1;
+        let (mut left, mut right) = (value >> half, value & mask);
+
+        for round in (0..ROUNDS).rev() {
+            (left, right) = (right ^ (self.round(round, left) & mask), left);
+        }
+
+        (left << half) | right
+    }
+
+    /// The Feistel round function: a SplitMix64 finalizer over the key, round and half.
+    fn round(&self, round: u64, half: u64) -> u64 {
+        let mut z = self.config.key ^ round.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ half;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Lowercases the scheme and host, drops default ports and resolves dot segments, so
+/// that equivalent spellings of a URL hash the same.
+pub fn normalize_url(long: &str) -> String {
+    url::Url::parse(long.trim())
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| long.trim().to_string())
+}
+
+/// Converts a big-endian number to base `base` digits, most significant first.
+fn to_digits(bytes: &[u8], base: usize) -> Vec<usize> {
+    let mut number = bytes.to_vec();
+    let mut digits = Vec::new();
+
+    while number.iter().any(|&byte| byte != 0) {
+        let mut remainder = 0;
+        for byte in number.iter_mut() {
+            let value = (remainder << 8) | *byte as usize;
+            *byte = (value / base) as u8;
+            remainder = value % base;
+        }
+        digits.push(remainder);
+    }
+
+    digits.reverse();
+    digits
+}
+
+fn look_alike(typed: char, replacement: char) -> bool {
+    LOOK_ALIKES
+        .iter()
+        .any(|group| group.contains(typed) && group.contains(replacement))
+}
+
+/// Returns the even bit width of the domain `value` is permuted in, and the lower
+/// bound of its size class within that domain.
+fn size_class(value: u64) -> (u32, u64) {
+    let bits = u64::BITS - value.leading_zeros();
+    if bits <= MIN_BITS {
+        return (MIN_BITS, 0);
+    }
+
+    let bits = bits + (bits & 1);
+    (bits, 1 << (bits - 2))
+}
@@ -1,5 +1,7 @@
 pub mod domain;
 pub mod outbound;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 
 #[macro_use]
 extern crate nestify;
@@ -0,0 +1,249 @@
+use chrono::{DateTime, Utc};
+use futures_util::{StreamExt, stream::BoxStream};
+use mongodb::bson::{self, Bson, Document};
+use tokio::sync::RwLock;
+use tracing::{debug, instrument, warn};
+
+use crate::domain::{
+    entities::url::Url,
+    metadata::url_indexes::{PartialFilter, UrlIndex, UrlIndexes},
+    repos::{
+        page::{Page, PageRequest, encode_cursor},
+        url_query::UrlQuery,
+        url_repo::{
+            DeleteUrlError, GetUrlError, InsertUrlError, ReplaceUrlError, UrlRepo, UrlRepoError,
+        },
+    },
+};
+
+/// A `UrlRepo` backed by a plain vector, intended for tests and embedded use.
+///
+/// Uniqueness is enforced the same way MongoDB enforces `UrlIndexes`: a missing
+/// field is indexed as `null`, a sparse index only skips documents that are
+/// missing every indexed field, and a partial index skips documents that don't
+/// match its filter.
+#[derive(Debug, Default)]
+pub struct InMemoryUrlRepo {
+    pub indexes: UrlIndexes,
+    pub urls: RwLock<Vec<Url>>,
+}
+
+impl UrlRepo for InMemoryUrlRepo {
+    type InsertOutput = ();
+
+    #[instrument(skip(self), fields(short = %short))]
+    async fn get(&self, short: &str) -> Result<Url, UrlRepoError> {
+        match self.urls.read().await.iter().find(|url| url.short == short) {
+            Some(url) => {
+                debug!("URL found: {}", url.short);
+                Ok(url.clone())
+            }
+            None => {
+                warn!("URL not found: {}", short);
+                Err(UrlRepoError::Get(GetUrlError::NotFound))
+            }
+        }
+    }
+
+    #[instrument(skip(self), fields(url = %url.long))]
+    async fn insert(&self, url: Url) -> Result<Self::InsertOutput, UrlRepoError> {
+        let mut urls = self.urls.write().await;
+
+        if self
+            .violates_indexes(&url, &urls, None)
+            .map_err(|err| UrlRepoError::Insert(InsertUrlError::ClientError(err)))?
+        {
+            warn!("Duplicate key error: URL already exists: {}", url.short);
+            return Err(UrlRepoError::Insert(InsertUrlError::AlreadyExists));
+        }
+
+        urls.push(url);
+        debug!("URL inserted successfully");
+
+        Ok(())
+    }
+
+    #[instrument(skip(self), fields(url = %url.short))]
+    async fn replace_if_exists(&self, url: Url) -> Result<(), UrlRepoError> {
+        let mut urls = self.urls.write().await;
+        let position = urls
+            .iter()
+            .position(|existing| existing.short == url.short)
+            .ok_or_else(|| UrlRepoError::Replace(ReplaceUrlError::NotFound(url.short.clone())))?;
+
+        if self
+            .violates_indexes(&url, &urls, Some(position))
+            .map_err(|err| UrlRepoError::Replace(ReplaceUrlError::ClientError(err)))?
+        {
+            return Err(UrlRepoError::Replace(ReplaceUrlError::ClientError(
+                anyhow::anyhow!("Duplicate key error: {}", url.short),
+            )));
+        }
+
+        urls[position] = url;
+
+        Ok(())
+    }
+
+    async fn find(&self, query: UrlQuery) -> Result<Option<Url>, UrlRepoError> {
+        self.urls
+            .read()
+            .await
+            .iter()
+            .find(|url| query.matches(url))
+            .cloned()
+            .map(Some)
+            .ok_or(UrlRepoError::Get(GetUrlError::NotFound))
+    }
+
+    #[instrument(skip(self), fields(short = %short))]
+    async fn delete(&self, short: &str) -> Result<Url, UrlRepoError> {
+        let mut urls = self.urls.write().await;
+        let position = urls
+            .iter()
+            .position(|url| url.short == short)
+            .ok_or_else(|| UrlRepoError::Delete(DeleteUrlError::NotFound(short.to_string())))?;
+
+        Ok(urls.remove(position))
+    }
+
+    async fn list_by_user(
+        &self,
+        user_id: &str,
+        page: PageRequest,
+    ) -> Result<Page<Url>, UrlRepoError> {
+        let after = page.start_after()?;
+
+        let mut items = self
+            .urls
+            .read()
+            .await
+            .iter()
+            .filter(|url| url.user_id == user_id)
+            .filter(|url| after.as_ref().is_none_or(|after| url.short > *after))
+            .cloned()
+            .collect::<Vec<_>>();
+        items.sort_by(|a, b| a.short.cmp(&b.short));
+
+        let next_cursor = if items.len() > page.limit as usize {
+            items.truncate(page.limit as usize);
+            items.last().map(|url| encode_cursor(&url.short))
+        } else {
+            None
+        };
+
+        Ok(Page::builder()
+            .items(items)
+            .maybe_next_cursor(next_cursor)
+            .build())
+    }
+
+    async fn count(&self) -> Result<u64, UrlRepoError> {
+        Ok(self.urls.read().await.len() as u64)
+    }
+
+    async fn count_by_user(&self, user_id: &str) -> Result<u64, UrlRepoError> {
+        Ok(self
+            .urls
+            .read()
+            .await
+            .iter()
+            .filter(|url| url.user_id == user_id)
+            .count() as u64)
+    }
+
+    /// Streams a snapshot taken when the scan starts; `batch_size` is ignored.
+    async fn scan(
+        &self,
+        _batch_size: u32,
+    ) -> Result<BoxStream<'static, Result<Url, UrlRepoError>>, UrlRepoError> {
+        let snapshot = self.urls.read().await.clone();
+
+        Ok(futures_util::stream::iter(snapshot.into_iter().map(Ok)).boxed())
+    }
+
+    async fn delete_expired(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<Url>, UrlRepoError> {
+        let mut urls = self.urls.write().await;
+        let mut expired = Vec::new();
+
+        urls.retain(|url| {
+            let is_expired = url.expiration_date.is_some_and(|date| date <= now);
+            if is_expired && expired.len() < limit as usize {
+                expired.push(url.clone());
+                return false;
+            }
+            true
+        });
+
+        Ok(expired)
+    }
+}
+
+impl InMemoryUrlRepo {
+    pub fn new(indexes: UrlIndexes) -> Self {
+        Self {
+            indexes,
+            urls: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Checks `url` against every unique index, ignoring the entry at `skip`
+    /// (the one being replaced).
+    fn violates_indexes(
+        &self,
+        url: &Url,
+        urls: &[Url],
+        skip: Option<usize>,
+    ) -> Result<bool, anyhow::Error> {
+        let candidate = bson::to_document(url)?;
+        let existing = urls
+            .iter()
+            .enumerate()
+            .filter(|(position, _)| Some(*position) != skip)
+            .map(|(_, url)| bson::to_document(url))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for index in self.indexes.values.iter().filter(|index| index.is_unique) {
+            let Some(candidate_key) = index_key(index, &candidate) else {
+                continue;
+            };
+
+            if existing
+                .iter()
+                .any(|document| index_key(index, document).as_ref() == Some(&candidate_key))
+            {
+                debug!("Unique index violated: {:?}", index.keys);
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/// Returns the indexed values of `document`, or `None` if a sparse or partial index skips it.
+fn index_key(index: &UrlIndex, document: &Document) -> Option<Vec<Bson>> {
+    if index.is_sparse && index.keys.iter().all(|key| !document.contains_key(key)) {
+        return None;
+    }
+
+    if let Some(PartialFilter::IsString(fields)) = index.partial_filter.as_ref()
+        && !fields
+            .iter()
+            .all(|field| matches!(document.get(field), Some(Bson::String(_))))
+    {
+        return None;
+    }
+
+    Some(
+        index
+            .keys
+            .iter()
+            .map(|key| document.get(key).cloned().unwrap_or(Bson::Null))
+            .collect(),
+    )
+}
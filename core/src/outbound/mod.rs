@@ -1,2 +1,4 @@
+pub mod in_memory;
 pub mod mongodb;
 pub mod redis;
+pub mod sql;
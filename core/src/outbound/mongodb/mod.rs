@@ -15,9 +15,23 @@ nest! {
         #[builder(into)]
         pub password: String,
         pub collections: HashMap<String, String>,
+        #[builder(default)]
+        #[serde(default)]
+        pub indexes: IndexManagement,
     }
 }
 
+/// How a service handles `UrlIndexes` when it boots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexManagement {
+    /// Apply `ensure_indexes` on every startup
+    #[default]
+    OnStartup,
+    /// Only check the plan on startup, apply it once with the `ensure-indexes` command
+    Manual,
+}
+
 impl MongoConfig {
     pub fn uri(&self) -> String {
         format!(
@@ -1,5 +1,6 @@
 use bson::bson;
-use futures_util::TryStreamExt;
+use chrono::{DateTime, Utc};
+use futures_util::{StreamExt, TryStreamExt, stream::BoxStream};
 use mongodb::{
     Collection, IndexModel,
     bson::{self, Document, doc},
@@ -12,16 +13,44 @@ use tracing::{debug, error, info, instrument, warn};
 
 use crate::domain::{
     entities::url::Url,
-    metadata::url_indexes::{UrlIndex, UrlIndexes},
-    repos::url_repo::{GetUrlError, InsertUrlError, ReplaceUrlError, UrlRepo, UrlRepoError},
+    metadata::url_indexes::{PartialFilter, UrlIndex, UrlIndexes, UrlIndexesError},
+    repos::{
+        page::{Page, PageRequest, encode_cursor},
+        url_query::UrlQuery,
+        url_repo::{
+            CountUrlsError, DeleteUrlError, GetUrlError, InsertUrlError, ListUrlsError,
+            ReplaceUrlError, UrlRepo, UrlRepoError,
+        },
+    },
 };
 
-use super::MongoConfig;
+use super::{IndexManagement, MongoConfig};
 
 #[derive(Debug, thiserror::Error)]
 pub enum MongoUrlRepoError {
     #[error("MongoDB Client Error: {0}")]
     ClientError(#[from] mongodb::error::Error),
+
+    #[error("Invalid Indexes: {0}")]
+    InvalidIndexes(#[from] UrlIndexesError),
+}
+
+/// Indexes earlier versions managed that `UrlIndexes` no longer has. They are the only
+/// ones `ensure_indexes` drops, indexes created by operators or other tools are kept.
+pub const LEGACY_INDEXES: &[&str] = &["user_id_1_long_1"];
+
+/// The changes `ensure_indexes` would make, by index name.
+#[derive(Debug, Default)]
+pub struct IndexPlan {
+    pub create: Vec<IndexModel>,
+    pub drop: Vec<String>,
+    pub keep: Vec<String>,
+}
+
+impl IndexPlan {
+    pub fn is_empty(&self) -> bool {
+        self.create.is_empty() && self.drop.is_empty()
+    }
 }
 
 nest! {
@@ -91,18 +120,176 @@ impl UrlRepo for MongoUrlRepo {
         Ok(())
     }
 
-    async fn find<T>(&self, query: T) -> Result<Option<Url>, UrlRepoError>
-    where
-        T: Into<Document> + Send + Sync,
-    {
-        let query: Document = query.into();
-
-        match self.collection.find_one(query.clone()).await {
+    async fn find(&self, query: UrlQuery) -> Result<Option<Url>, UrlRepoError> {
+        match self.collection.find_one(query.into_filter()).await {
             Ok(Some(url)) => Ok(Some(url)),
             Ok(None) => Err(UrlRepoError::Get(GetUrlError::NotFound)),
             Err(e) => Err(UrlRepoError::Get(GetUrlError::ClientError(e.into()))),
         }
     }
+
+    #[instrument(skip(self), fields(short = %short))]
+    async fn delete(&self, short: &str) -> Result<Url, UrlRepoError> {
+        let url = self
+            .collection
+            .find_one_and_delete(doc! {"short": short})
+            .await
+            .map_err(|err| UrlRepoError::Delete(DeleteUrlError::ClientError(err.into())))?
+            .ok_or(UrlRepoError::Delete(DeleteUrlError::NotFound(
+                short.to_string(),
+            )))?;
+
+        debug!("URL deleted: {}", url.short);
+
+        Ok(url)
+    }
+
+    #[instrument(skip(self))]
+    async fn list_by_user(
+        &self,
+        user_id: &str,
+        page: PageRequest,
+    ) -> Result<Page<Url>, UrlRepoError> {
+        let after = page.start_after()?;
+
+        let mut filter = doc! {"userId": user_id};
+        if let Some(after) = after {
+            filter.insert("short", doc! {"$gt": after});
+        }
+
+        // Fetch one extra document to know whether another page follows.
+        let mut items = self
+            .collection
+            .find(filter)
+            .sort(doc! {"short": 1})
+            .limit(i64::from(page.limit) + 1)
+            .await
+            .map_err(|err| UrlRepoError::List(ListUrlsError::ClientError(err.into())))?
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(|err| UrlRepoError::List(ListUrlsError::ClientError(err.into())))?;
+
+        let next_cursor = if items.len() > page.limit as usize {
+            items.truncate(page.limit as usize);
+            items.last().map(|url| encode_cursor(&url.short))
+        } else {
+            None
+        };
+
+        Ok(Page::builder()
+            .items(items)
+            .maybe_next_cursor(next_cursor)
+            .build())
+    }
+
+    #[instrument(skip(self))]
+    async fn count(&self) -> Result<u64, UrlRepoError> {
+        self.collection
+            .count_documents(doc! {})
+            .await
+            .map_err(|err| UrlRepoError::Count(CountUrlsError::ClientError(err.into())))
+    }
+
+    #[instrument(skip(self))]
+    async fn count_by_user(&self, user_id: &str) -> Result<u64, UrlRepoError> {
+        self.collection
+            .count_documents(doc! {"userId": user_id})
+            .await
+            .map_err(|err| UrlRepoError::Count(CountUrlsError::ClientError(err.into())))
+    }
+
+    #[instrument(skip(self))]
+    async fn scan(
+        &self,
+        batch_size: u32,
+    ) -> Result<BoxStream<'static, Result<Url, UrlRepoError>>, UrlRepoError> {
+        let cursor = self
+            .collection
+            .find(doc! {})
+            .batch_size(batch_size)
+            .await
+            .map_err(|err| UrlRepoError::List(ListUrlsError::ClientError(err.into())))?;
+
+        Ok(cursor
+            .map_err(|err| UrlRepoError::List(ListUrlsError::ClientError(err.into())))
+            .boxed())
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_expired(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<Url>, UrlRepoError> {
+        // Expirations are stored as fixed-width UTC strings, which sort chronologically
+        let expired_filter = doc! {"expirationDate": {"$lte": Url::format_expiration_date(&now)}};
+
+        let expired = self
+            .collection
+            .find(expired_filter.clone())
+            .limit(i64::from(limit))
+            .await
+            .map_err(|err| UrlRepoError::Delete(DeleteUrlError::ClientError(err.into())))?
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(|err| UrlRepoError::Delete(DeleteUrlError::ClientError(err.into())))?;
+
+        if expired.is_empty() {
+            return Ok(expired);
+        }
+
+        let shorts = expired
+            .iter()
+            .map(|url| url.short.as_str())
+            .collect::<Vec<_>>();
+        let mut filter = expired_filter;
+        filter.insert("short", doc! {"$in": shorts});
+
+        // Re-check the expiration so a link renewed in the meantime is kept
+        let result = self
+            .collection
+            .delete_many(filter)
+            .await
+            .map_err(|err| UrlRepoError::Delete(DeleteUrlError::ClientError(err.into())))?;
+        debug!("Deleted {} expired URLs", result.deleted_count);
+
+        Ok(expired)
+    }
+}
+
+pub trait IntoFilter {
+    fn into_filter(self) -> Document;
+}
+
+impl IntoFilter for UrlQuery {
+    fn into_filter(self) -> Document {
+        match self {
+            UrlQuery::ByShort(short) => doc! { "short": short },
+            UrlQuery::ByAlias(alias) => doc! { "alias": alias },
+            UrlQuery::ByShortOrAlias(code) => doc! {
+                "$or": [
+                    { "alias": &code },
+                    { "short": &code },
+                ]
+            },
+            UrlQuery::ByUserAndLong { user_id, long } => doc! {
+                "userId": user_id,
+                "long": long,
+            },
+        }
+    }
+}
+
+impl IntoFilter for PartialFilter {
+    fn into_filter(self) -> Document {
+        match self {
+            PartialFilter::IsString(fields) => Document::from_iter(
+                fields
+                    .into_iter()
+                    .map(|field| (field, bson!({ "$type": "string" }))),
+            ),
+        }
+    }
 }
 
 pub trait IntoIndexModel {
@@ -115,6 +302,7 @@ impl IntoIndexModel for UrlIndex {
         let options = IndexOptions::builder()
             .unique(Some(self.is_unique))
             .sparse(Some(self.is_sparse))
+            .partial_filter_expression(self.partial_filter.map(IntoFilter::into_filter))
             .build();
 
         IndexModel::builder()
@@ -124,6 +312,14 @@ impl IntoIndexModel for UrlIndex {
     }
 }
 
+/// The name MongoDB gives an index created without one, e.g. `user_id_1_long_1`.
+fn default_index_name(keys: &Document) -> String {
+    keys.iter()
+        .map(|(key, value)| format!("{}_{}", key, value))
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
 impl MongoUrlRepo {
     #[instrument(skip(config))]
     pub async fn new(config: MongoConfig) -> Result<Self, MongoUrlRepoError> {
@@ -146,14 +342,43 @@ impl MongoUrlRepo {
         Ok(MongoUrlRepo { config, collection })
     }
 
+    /// Handles indexes at service startup according to `MongoConfig::indexes`.
+    #[instrument(skip(self))]
+    pub async fn ensure_indexes_on_startup(&self) -> Result<(), MongoUrlRepoError> {
+        match self.config.indexes {
+            IndexManagement::OnStartup => {
+                self.ensure_indexes().await?;
+            }
+            IndexManagement::Manual => {
+                let plan = self.plan_indexes().await?;
+                if !plan.is_empty() {
+                    warn!("Indexes are out of date, run the `ensure-indexes` command");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates missing indexes and rebuilds mismatched ones, see `plan_indexes`.
     #[instrument(skip(self))]
-    pub async fn ensure_indexes(&self) -> Result<(), MongoUrlRepoError> {
+    pub async fn ensure_indexes(&self) -> Result<IndexPlan, MongoUrlRepoError> {
+        let plan = self.plan_indexes().await?;
+        self.apply_index_plan(&plan).await?;
+
+        Ok(plan)
+    }
+
+    /// Compares `UrlIndexes` with the indexes in the collection without changing anything.
+    #[instrument(skip(self))]
+    pub async fn plan_indexes(&self) -> Result<IndexPlan, MongoUrlRepoError> {
         let url_indexes = UrlIndexes::default();
         info!(
-            "Ensuring indexes for collection: {}",
+            "Planning indexes for collection: {}",
             self.collection.name()
         );
         info!("Indexes: {:#?}", url_indexes);
+        url_indexes.validate()?;
 
         let mongo_existing_indexes = self
             .collection
@@ -164,6 +389,21 @@ impl MongoUrlRepo {
 
         info!("Mongo existing indexes: {:#?}", mongo_existing_indexes);
 
+        let mut plan = IndexPlan::default();
+
+        // Indexes earlier versions created over fields `Url` no longer serializes only
+        // ever see nulls, drop them. Any other index was created by someone else.
+        for existing in &mongo_existing_indexes {
+            if let Some(name) = existing
+                .options
+                .as_ref()
+                .and_then(|opts| opts.name.as_ref())
+                .filter(|name| LEGACY_INDEXES.contains(&name.as_str()))
+            {
+                plan.drop.push(name.clone());
+            }
+        }
+
         for new in url_indexes.values {
             // Find matching existing index by key
             let new_keys = Document::from_iter(new.keys.iter().map(|key| (key.clone(), bson!(1))));
@@ -172,38 +412,86 @@ impl MongoUrlRepo {
                 .iter()
                 .find(|existing| existing.keys == new_keys);
 
+            // An index listed without options has the defaults, so it can still differ
             if let Some(existing) = matched {
-                if let Some(existing_opts) = existing.options.as_ref() {
-                    let same_unique = existing_opts.unique.unwrap_or_default() == new.is_unique;
-                    let same_sparse = existing_opts.sparse.unwrap_or_default() == new.is_sparse;
-
-                    if same_unique && same_sparse {
-                        continue;
-                    }
+                let existing_opts = existing.options.clone().unwrap_or_default();
+                let name = existing_opts
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| default_index_name(&existing.keys));
+                let same_unique = existing_opts.unique.unwrap_or_default() == new.is_unique;
+                let same_sparse = existing_opts.sparse.unwrap_or_default() == new.is_sparse;
+                let same_partial_filter = existing_opts.partial_filter_expression
+                    == new.partial_filter.clone().map(IntoFilter::into_filter);
 
-                    info!(
-                        "Dropping index: {}",
-                        existing_opts
-                            .name
-                            .as_ref()
-                            .unwrap_or(&"unknown_index_name".to_string())
-                    );
-                    self.collection
-                        .drop_index(
-                            existing_opts
-                                .name
-                                .as_ref()
-                                .unwrap_or(&"unknown_index_name".to_string()),
-                        )
-                        .await?;
+                if same_unique && same_sparse && same_partial_filter {
+                    plan.keep.push(name);
+                    continue;
                 }
+
+                plan.drop.push(name);
             }
 
-            let mongo_index = new.into_index_model();
+            plan.create.push(new.into_index_model());
+        }
+
+        info!("Index plan: {:#?}", plan);
+
+        Ok(plan)
+    }
+
+    /// Drops, then creates, the indexes listed in `plan`.
+    #[instrument(skip(self, plan))]
+    pub async fn apply_index_plan(&self, plan: &IndexPlan) -> Result<(), MongoUrlRepoError> {
+        for name in plan.drop.iter() {
+            info!("Dropping index: {}", name);
+            self.collection.drop_index(name).await?;
+        }
+
+        for mongo_index in plan.create.iter() {
             info!("Creating index: {:#?}", mongo_index);
-            self.collection.create_index(mongo_index).await?;
+            self.collection.create_index(mongo_index.clone()).await?;
         }
 
         Ok(())
     }
+
+    /// Rewrites legacy `YYYY-MM-DD` expirations as the UTC instant they end at,
+    /// the start of the following day. Returns how many documents were updated.
+    ///
+    /// Run this before the expiration sweeper sees legacy documents: a date-only
+    /// string sorts before every timestamp on the same day, so it would be swept a
+    /// day early.
+    #[instrument(skip(self))]
+    pub async fn migrate_expiration_dates(&self) -> Result<u64, MongoUrlRepoError> {
+        let filter = doc! {"expirationDate": {"$regex": r"^\d{4}-\d{2}-\d{2}$"}};
+        let pipeline = vec![doc! {
+            "$set": {
+                "expirationDate": {
+                    "$dateToString": {
+                        "format": "%Y-%m-%dT%H:%M:%SZ",
+                        "timezone": "UTC",
+                        "date": {
+                            "$dateAdd": {
+                                "startDate": {
+                                    "$dateFromString": {
+                                        "dateString": "$expirationDate",
+                                        "format": "%Y-%m-%d",
+                                        "timezone": "UTC",
+                                    }
+                                },
+                                "unit": "day",
+                                "amount": 1,
+                            }
+                        },
+                    }
+                }
+            }
+        }];
+
+        let result = self.collection.update_many(filter, pipeline).await?;
+        info!("Migrated {} expiration dates", result.modified_count);
+
+        Ok(result.modified_count)
+    }
 }
@@ -0,0 +1,141 @@
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use redis::{
+    Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, Value,
+    aio::{ConnectionLike, MultiplexedConnection},
+    cluster_async::ClusterConnection,
+    sentinel::SentinelClient,
+};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RedisConnectionError {
+    #[error("Redis Client Error: {0}")]
+    RedisClientError(#[from] redis::RedisError),
+
+    #[error("No `{0}` database in the Redis config")]
+    MissingDb(String),
+
+    #[error("Redis Cluster only has database 0, but `{0}` is {1}")]
+    ClusterDb(String, u8),
+}
+
+/// A connection to whichever topology `RedisConfig` describes.
+///
+/// Cloning is cheap and every clone shares the same sockets, so callers clone it per
+/// call instead of locking it.
+#[derive(Clone)]
+pub enum RedisConnection {
+    Node(MultiplexedConnection),
+    Sentinel(SentinelConnection),
+    Cluster(ClusterConnection),
+}
+
+/// A connection to the master the sentinels report, which is resolved again once it
+/// fails or turns out to be a replica after a failover.
+#[derive(Clone)]
+pub struct SentinelConnection {
+    pub client: Arc<Mutex<SentinelClient>>,
+    pub conn: Arc<RwLock<MultiplexedConnection>>,
+}
+
+impl fmt::Debug for RedisConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RedisConnection::Node(conn) => f.debug_tuple("Node").field(conn).finish(),
+            RedisConnection::Sentinel(_) => f.write_str("Sentinel"),
+            RedisConnection::Cluster(_) => f.write_str("Cluster"),
+        }
+    }
+}
+
+impl SentinelConnection {
+    pub async fn new(mut client: SentinelClient) -> Result<Self, RedisConnectionError> {
+        let conn = client.get_async_connection().await?;
+
+        Ok(SentinelConnection {
+            client: Arc::new(Mutex::new(client)),
+            conn: Arc::new(RwLock::new(conn)),
+        })
+    }
+
+    fn current(&self) -> MultiplexedConnection {
+        self.conn
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Asks the sentinels for the master again if `err` means we lost it. The failed
+    /// call still returns its error, the next one goes to the new master.
+    async fn recover(&self, err: &RedisError) {
+        let lost_master = err.is_io_error()
+            || err.is_connection_dropped()
+            || err.is_connection_refusal()
+            || err.kind() == ErrorKind::ReadOnly;
+        if !lost_master {
+            return;
+        }
+
+        warn!("Lost the Redis master, asking the sentinels again: {}", err);
+        match self.client.lock().await.get_async_connection().await {
+            Ok(conn) => {
+                *self
+                    .conn
+                    .write()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) = conn
+            }
+            Err(err) => warn!("Failed to reconnect to the Redis master: {}", err),
+        }
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            RedisConnection::Node(conn) => conn.req_packed_command(cmd),
+            RedisConnection::Cluster(conn) => conn.req_packed_command(cmd),
+            RedisConnection::Sentinel(sentinel) => Box::pin(async move {
+                let result = sentinel.current().req_packed_command(cmd).await;
+                if let Err(err) = &result {
+                    sentinel.recover(err).await;
+                }
+                result
+            }),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            RedisConnection::Node(conn) => conn.req_packed_commands(cmd, offset, count),
+            RedisConnection::Cluster(conn) => conn.req_packed_commands(cmd, offset, count),
+            RedisConnection::Sentinel(sentinel) => Box::pin(async move {
+                let result = sentinel
+                    .current()
+                    .req_packed_commands(cmd, offset, count)
+                    .await;
+                if let Err(err) = &result {
+                    sentinel.recover(err).await;
+                }
+                result
+            }),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            RedisConnection::Node(conn) => conn.get_db(),
+            RedisConnection::Cluster(conn) => conn.get_db(),
+            RedisConnection::Sentinel(sentinel) => sentinel.current().get_db(),
+        }
+    }
+}
@@ -1,10 +1,209 @@
-use std::collections::HashMap;
+pub mod connection;
+pub mod url_cache;
+
+use std::{collections::HashMap, num::NonZeroU64};
+
+use chrono::{DateTime, Utc};
+use redis::{
+    Client, ConnectionAddr, ConnectionInfo, ErrorKind, RedisConnectionInfo, RedisError, TlsMode,
+    cluster::ClusterClient,
+    sentinel::{Sentinel, SentinelClient, SentinelNodeConnectionInfo, SentinelServerType},
+};
+
+use crate::domain::entities::url::Url;
+
+use self::connection::{RedisConnection, RedisConnectionError, SentinelConnection};
+
+/// The `dbs` entry holding the URL cache both services share
+pub const URL_CACHE_DB: &str = "url_cache";
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
 #[serde(rename_all = "snake_case")]
 pub struct RedisConfig {
+    /// The node to connect to, unless `topology` lists other nodes
     #[builder(into)]
     pub host: String,
     pub port: u16,
     pub dbs: HashMap<String, u8>,
+    /// Prefixed to every key, so that several deployments can share a database
+    #[builder(into)]
+    pub namespace: String,
+    /// The longest a cached URL is kept, also for URLs that never expire
+    pub max_ttl_secs: NonZeroU64,
+    #[builder(default)]
+    #[serde(default)]
+    pub topology: RedisTopology,
+    /// The ACL user to authenticate as, `default` if only `password` is set
+    #[builder(into)]
+    #[serde(default)]
+    pub username: Option<String>,
+    #[builder(into)]
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Connect to every node, sentinels included, over TLS
+    #[builder(default)]
+    #[serde(default)]
+    pub tls: bool,
+}
+
+/// How the Redis nodes are deployed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum RedisTopology {
+    /// The single node at `host` and `port`
+    #[default]
+    Standalone,
+    /// Whichever node the `nodes` sentinels report as the `master_name` master
+    Sentinel {
+        master_name: String,
+        nodes: Vec<RedisNode>,
+        /// The sentinels' own password, if they require one
+        #[serde(default)]
+        password: Option<String>,
+    },
+    /// A cluster discovered from the `nodes` it starts with. Clusters only have
+    /// database 0, and keys are hash-tagged so that lookups stay in one slot.
+    Cluster { nodes: Vec<RedisNode> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedisNode {
+    pub host: String,
+    pub port: u16,
+}
+
+impl RedisConfig {
+    /// Connects to the database registered as `db` in `dbs`.
+    pub async fn connect(&self, db: &str) -> Result<RedisConnection, RedisConnectionError> {
+        let index = *self
+            .dbs
+            .get(db)
+            .ok_or_else(|| RedisConnectionError::MissingDb(db.to_string()))?;
+
+        Ok(match &self.topology {
+            RedisTopology::Standalone => RedisConnection::Node(
+                Client::open(self.connection_info(&self.host, self.port, index))?
+                    .get_multiplexed_async_connection()
+                    .await?,
+            ),
+            RedisTopology::Sentinel {
+                master_name,
+                nodes,
+                password,
+            } => {
+                let client = SentinelClient::build(
+                    self.sentinels(nodes, password),
+                    master_name.clone(),
+                    Some(self.master_info(index)),
+                    SentinelServerType::Master,
+                )?;
+
+                RedisConnection::Sentinel(SentinelConnection::new(client).await?)
+            }
+            RedisTopology::Cluster { nodes } => {
+                if index != 0 {
+                    return Err(RedisConnectionError::ClusterDb(db.to_string(), index));
+                }
+
+                RedisConnection::Cluster(
+                    ClusterClient::builder(
+                        nodes
+                            .iter()
+                            .map(|node| self.connection_info(&node.host, node.port, index)),
+                    )
+                    .build()?
+                    .get_async_connection()
+                    .await?,
+                )
+            }
+        })
+    }
+
+    /// A client to subscribe with. Messages published on any node reach every other
+    /// one, so any node of a cluster will do.
+    pub async fn pubsub_client(&self) -> Result<Client, RedisConnectionError> {
+        Ok(match &self.topology {
+            RedisTopology::Standalone => {
+                Client::open(self.connection_info(&self.host, self.port, 0))?
+            }
+            RedisTopology::Sentinel {
+                master_name,
+                nodes,
+                password,
+            } => {
+                Sentinel::build(self.sentinels(nodes, password))?
+                    .async_master_for(master_name, Some(&self.master_info(0)))
+                    .await?
+            }
+            RedisTopology::Cluster { nodes } => {
+                let node = nodes.first().ok_or_else(|| {
+                    RedisError::from((ErrorKind::InvalidClientConfig, "No cluster nodes"))
+                })?;
+                Client::open(self.connection_info(&node.host, node.port, 0))?
+            }
+        })
+    }
+
+    /// How to reach database `index` on the node at `host` and `port`, with the
+    /// configured credentials and TLS.
+    pub fn connection_info(&self, host: &str, port: u16, index: u8) -> ConnectionInfo {
+        ConnectionInfo {
+            addr: self.addr(host, port),
+            redis: self.redis_connection_info(index),
+        }
+    }
+
+    fn sentinels(&self, nodes: &[RedisNode], password: &Option<String>) -> Vec<ConnectionInfo> {
+        nodes
+            .iter()
+            .map(|node| ConnectionInfo {
+                addr: self.addr(&node.host, node.port),
+                redis: RedisConnectionInfo {
+                    password: password.clone(),
+                    ..Default::default()
+                },
+            })
+            .collect()
+    }
+
+    fn master_info(&self, index: u8) -> SentinelNodeConnectionInfo {
+        SentinelNodeConnectionInfo {
+            tls_mode: self.tls.then_some(TlsMode::Secure),
+            redis_connection_info: Some(self.redis_connection_info(index)),
+        }
+    }
+
+    fn addr(&self, host: &str, port: u16) -> ConnectionAddr {
+        if self.tls {
+            ConnectionAddr::TcpTls {
+                host: host.to_string(),
+                port,
+                insecure: false,
+                tls_params: None,
+            }
+        } else {
+            ConnectionAddr::Tcp(host.to_string(), port)
+        }
+    }
+
+    fn redis_connection_info(&self, index: u8) -> RedisConnectionInfo {
+        RedisConnectionInfo {
+            db: index.into(),
+            username: self.username.clone(),
+            password: self.password.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// How long to cache `url` for: until it expires, capped by `max_ttl_secs`.
+    /// Returns `None` once it has expired, as it must not be cached any more.
+    pub fn ttl_secs(&self, url: &Url, now: DateTime<Utc>) -> Option<u64> {
+        match url.expiration_date {
+            Some(expiration_date) => {
+                let left = (expiration_date - now).num_seconds();
+                (left > 0).then(|| (left as u64).min(self.max_ttl_secs.get()))
+            }
+            None => Some(self.max_ttl_secs.get()),
+        }
+    }
 }
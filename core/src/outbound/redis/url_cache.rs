@@ -0,0 +1,271 @@
+use chrono::Utc;
+use futures_util::{Stream, StreamExt, future::try_join_all};
+use redis::{AsyncCommands, ExpireOption, Pipeline};
+use tracing::{debug, instrument, warn};
+
+use crate::domain::entities::{Entity, url::Url};
+
+use super::{
+    RedisConfig, RedisTopology, URL_CACHE_DB,
+    connection::{RedisConnection, RedisConnectionError},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RedisUrlCacheError {
+    #[error("Redis Client Error: {0}")]
+    RedisClientError(#[from] redis::RedisError),
+
+    #[error("Connection Error: {0}")]
+    ConnectionError(#[from] RedisConnectionError),
+
+    #[error("Internal Error: {0}")]
+    InternalError(#[from] anyhow::Error),
+}
+
+/// The keys URLs are cached under. Every entry holds the URL as `Entity` JSON.
+///
+/// With `hash_tags`, the code or user ID is wrapped in `{}`, so that a cluster keeps
+/// the `short:` and `alias:` keys of one code in the same slot for `get_by_code`.
+///
+/// This groups keys by the code being looked up, not by link: a link's short code
+/// and alias land in different slots and are written in separate pipelines, not
+/// atomically. A reader only knows the code it was given, so tagging by the link's
+/// short code would send an alias lookup to the wrong slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlCacheKeys {
+    pub namespace: String,
+    pub hash_tags: bool,
+}
+
+impl UrlCacheKeys {
+    pub fn short(&self, short: &str) -> String {
+        format!("{}:short:{}", self.namespace, self.tag(short))
+    }
+
+    pub fn alias(&self, alias: &str) -> String {
+        format!("{}:alias:{}", self.namespace, self.tag(alias))
+    }
+
+    /// A hash of the user's URLs, keyed by long URL
+    pub fn user_urls(&self, user_id: &str) -> String {
+        format!("{}:user:{}:urls", self.namespace, self.tag(user_id))
+    }
+
+    /// The channel `evict` publishes the evicted codes on
+    pub fn evictions(&self) -> String {
+        format!("{}:evictions", self.namespace)
+    }
+
+    /// Every key `url` is cached under, except the user hash.
+    pub fn codes(&self, url: &Url) -> Vec<String> {
+        std::iter::once(self.short(&url.short))
+            .chain(url.alias.as_deref().map(|alias| self.alias(alias)))
+            .collect()
+    }
+
+    fn tag(&self, id: &str) -> String {
+        if self.hash_tags {
+            format!("{{{}}}", id)
+        } else {
+            id.to_string()
+        }
+    }
+}
+
+/// The URL cache shared by the shorten and redirect services.
+///
+/// Every call clones the multiplexed connection, which only clones a handle to the
+/// same socket, so concurrent calls are pipelined rather than queued behind a lock.
+#[derive(Debug, Clone)]
+pub struct RedisUrlCache {
+    pub config: RedisConfig,
+    pub keys: UrlCacheKeys,
+    pub conn: RedisConnection,
+}
+
+impl RedisUrlCache {
+    pub async fn new(config: RedisConfig) -> Result<Self, RedisUrlCacheError> {
+        let conn = config.connect(URL_CACHE_DB).await?;
+
+        Ok(RedisUrlCache {
+            keys: UrlCacheKeys {
+                namespace: config.namespace.clone(),
+                hash_tags: matches!(config.topology, RedisTopology::Cluster { .. }),
+            },
+            config,
+            conn,
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_by_short(&self, short: &str) -> Result<Option<Url>, RedisUrlCacheError> {
+        self.get(self.keys.short(short)).await
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_by_alias(&self, alias: &str) -> Result<Option<Url>, RedisUrlCacheError> {
+        self.get(self.keys.alias(alias)).await
+    }
+
+    /// Looks `code` up as a short code and as an alias in a single `MGET`, preferring
+    /// the short code.
+    #[instrument(skip(self))]
+    pub async fn get_by_code(&self, code: &str) -> Result<Option<Url>, RedisUrlCacheError> {
+        let (short, alias): (Option<String>, Option<String>) = self
+            .conn
+            .clone()
+            .mget(&[self.keys.short(code), self.keys.alias(code)])
+            .await?;
+        debug!(
+            "Cache {} for {}",
+            if short.is_some() || alias.is_some() {
+                "hit"
+            } else {
+                "miss"
+            },
+            code
+        );
+
+        short.or(alias).as_deref().map(decode).transpose()
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_by_long_url(
+        &self,
+        long_url: &str,
+        user_id: &str,
+    ) -> Result<Option<Url>, RedisUrlCacheError> {
+        let value: Option<String> = self
+            .conn
+            .clone()
+            .hget(self.keys.user_urls(user_id), long_url)
+            .await?;
+
+        value.as_deref().map(decode).transpose()
+    }
+
+    /// Caches `url` under every key until it expires, capped by `max_ttl_secs`.
+    /// Expired URLs aren't cached.
+    #[instrument(skip(self), fields(short = %url.short))]
+    pub async fn set(&self, url: &Url) -> Result<(), RedisUrlCacheError> {
+        let Some(ttl) = self.config.ttl_secs(url, Utc::now()) else {
+            debug!("Not caching expired URL: {}", url.short);
+            return Ok(());
+        };
+
+        let value = url.to_json()?;
+        let user_urls = self.keys.user_urls(&url.user_id);
+
+        let mut pipes = self
+            .keys
+            .codes(url)
+            .into_iter()
+            .map(|key| {
+                let mut pipe = redis::pipe();
+                pipe.set_ex(key, &value, ttl).ignore();
+                pipe
+            })
+            .collect::<Vec<_>>();
+        // Each user's URLs share the hash, so every field expires on its own
+        let mut pipe = redis::pipe();
+        pipe.hset(&user_urls, &url.long, &value)
+            .ignore()
+            .hexpire(&user_urls, ttl as i64, ExpireOption::NONE, &url.long)
+            .ignore();
+        pipes.push(pipe);
+
+        self.query_all(pipes).await?;
+        debug!("Cached URL for {}s: {}", ttl, url.short);
+
+        Ok(())
+    }
+
+    /// Removes every entry `set` wrote for `url`, then announces its codes on the
+    /// `evictions` channel for caches kept in front of this one.
+    #[instrument(skip(self), fields(short = %url.short))]
+    pub async fn evict(&self, url: &Url) -> Result<(), RedisUrlCacheError> {
+        let mut pipes = self
+            .keys
+            .codes(url)
+            .into_iter()
+            .map(|key| {
+                let mut pipe = redis::pipe();
+                pipe.del(key).ignore();
+                pipe
+            })
+            .collect::<Vec<_>>();
+        let mut pipe = redis::pipe();
+        pipe.hdel(self.keys.user_urls(&url.user_id), &url.long)
+            .ignore();
+        pipes.push(pipe);
+
+        self.query_all(pipes).await?;
+
+        let codes = std::iter::once(&url.short)
+            .chain(url.alias.as_ref())
+            .collect::<Vec<_>>();
+        let () = self
+            .conn
+            .clone()
+            .publish(
+                self.keys.evictions(),
+                serde_json::to_string(&codes).map_err(anyhow::Error::from)?,
+            )
+            .await?;
+        debug!("Evicted URL: {}", url.short);
+
+        Ok(())
+    }
+
+    /// Subscribes to the codes `evict` removes, one batch per evicted URL.
+    #[instrument(skip(self))]
+    pub async fn evictions(
+        &self,
+    ) -> Result<impl Stream<Item = Vec<String>> + Send + use<>, RedisUrlCacheError> {
+        let mut pubsub = self
+            .config
+            .pubsub_client()
+            .await?
+            .get_async_pubsub()
+            .await?;
+        pubsub.subscribe(self.keys.evictions()).await?;
+
+        Ok(pubsub.into_on_message().filter_map(|msg| async move {
+            let codes = msg
+                .get_payload::<String>()
+                .ok()
+                .and_then(|payload| serde_json::from_str(&payload).ok());
+            if codes.is_none() {
+                warn!("Ignoring malformed eviction: {:?}", msg);
+            }
+            codes
+        }))
+    }
+
+    /// Sends `pipes` concurrently. Each one only touches a single key, so that it
+    /// maps to one cluster slot, while a single node still pipelines them together.
+    async fn query_all(&self, pipes: Vec<Pipeline>) -> Result<(), RedisUrlCacheError> {
+        try_join_all(pipes.iter().map(|pipe| {
+            let mut conn = self.conn.clone();
+            async move { pipe.query_async::<()>(&mut conn).await }
+        }))
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get(&self, key: String) -> Result<Option<Url>, RedisUrlCacheError> {
+        let value: Option<String> = self.conn.clone().get(&key).await?;
+        debug!(
+            "Cache {} for {}",
+            if value.is_some() { "hit" } else { "miss" },
+            key
+        );
+
+        value.as_deref().map(decode).transpose()
+    }
+}
+
+fn decode(json: &str) -> Result<Url, RedisUrlCacheError> {
+    Ok(Url::from_json(json)?)
+}
@@ -0,0 +1,586 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures_util::{StreamExt, TryStreamExt, stream::BoxStream};
+use sqlx::{
+    AnyPool, Row,
+    any::{AnyPoolOptions, AnyQueryResult, AnyRow},
+};
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::domain::{
+    entities::url::Url,
+    metadata::url_indexes::{PartialFilter, UrlIndex, UrlIndexes, UrlIndexesError},
+    repos::{
+        page::{Page, PageRequest, encode_cursor},
+        url_query::UrlQuery,
+        url_repo::{
+            CountUrlsError, DeleteUrlError, GetUrlError, InsertUrlError, ListUrlsError,
+            ReplaceUrlError, UrlRepo, UrlRepoError,
+        },
+    },
+};
+
+use super::{SqlConfig, SqlDialect};
+
+const COLUMNS: &str =
+    "long, short, alias, expiration_date, created_at, updated_at, user_id, fallback_url";
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SqlUrlRepoError {
+    #[error("SQL Client Error: {0}")]
+    ClientError(#[from] sqlx::Error),
+
+    #[error("Unsupported SQL URL: {0}")]
+    UnsupportedUrl(String),
+
+    #[error("Invalid Indexes: {0}")]
+    InvalidIndexes(#[from] UrlIndexesError),
+}
+
+nest! {
+    #[derive(Debug)]*
+    pub struct SqlUrlRepo {
+        pub config: SqlConfig,
+        pub dialect: SqlDialect,
+        pub table: String,
+        pub pool: AnyPool,
+    }
+}
+
+impl UrlRepo for SqlUrlRepo {
+    type InsertOutput = AnyQueryResult;
+
+    #[instrument(skip(self), fields(short = %short))]
+    async fn get(&self, short: &str) -> Result<Url, UrlRepoError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM {} WHERE short = $1",
+            COLUMNS, self.table
+        ))
+        .bind(short)
+        .fetch_optional(&self.pool)
+        .await;
+
+        match row {
+            Ok(Some(row)) => {
+                let url = url_from_row(&row)
+                    .map_err(|err| UrlRepoError::Get(GetUrlError::InternalError(err)))?;
+                debug!("URL found: {}", url.short);
+                Ok(url)
+            }
+            Ok(None) => {
+                warn!("URL not found: {}", short);
+                Err(UrlRepoError::Get(GetUrlError::NotFound))
+            }
+            Err(e) => {
+                error!("SQL select error: {:?}", e);
+                Err(UrlRepoError::Get(GetUrlError::ClientError(e.into())))
+            }
+        }
+    }
+
+    #[instrument(skip(self), fields(url = %url.long))]
+    async fn insert(&self, url: Url) -> Result<Self::InsertOutput, UrlRepoError> {
+        let result = sqlx::query(&format!(
+            "INSERT INTO {} ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            self.table, COLUMNS
+        ))
+        .bind(url.long)
+        .bind(url.short)
+        .bind(url.alias)
+        .bind(
+            url.expiration_date
+                .as_ref()
+                .map(Url::format_expiration_date),
+        )
+        .bind(url.created_at.format(DATE_TIME_FORMAT).to_string())
+        .bind(url.updated_at.format(DATE_TIME_FORMAT).to_string())
+        .bind(url.user_id)
+        .bind(url.fallback_url)
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(result) => {
+                debug!("URL inserted successfully");
+                Ok(result)
+            }
+            Err(sqlx::Error::Database(db_error)) if db_error.is_unique_violation() => {
+                warn!("Duplicate key error: URL already exists: {}", db_error);
+                Err(UrlRepoError::Insert(InsertUrlError::AlreadyExists))
+            }
+            Err(err) => {
+                error!("SQL insert error: {:?}", err);
+                Err(UrlRepoError::Insert(InsertUrlError::ClientError(
+                    err.into(),
+                )))
+            }
+        }
+    }
+
+    #[instrument(skip(self), fields(url = %url.short))]
+    async fn replace_if_exists(&self, url: Url) -> Result<(), UrlRepoError> {
+        let result = sqlx::query(&format!(
+            "UPDATE {} SET long = $1, alias = $2, expiration_date = $3, created_at = $4, \
+             updated_at = $5, user_id = $6, fallback_url = $7 WHERE short = $8",
+            self.table
+        ))
+        .bind(url.long)
+        .bind(url.alias)
+        .bind(
+            url.expiration_date
+                .as_ref()
+                .map(Url::format_expiration_date),
+        )
+        .bind(url.created_at.format(DATE_TIME_FORMAT).to_string())
+        .bind(url.updated_at.format(DATE_TIME_FORMAT).to_string())
+        .bind(url.user_id)
+        .bind(url.fallback_url)
+        .bind(url.short.clone())
+        .execute(&self.pool)
+        .await
+        .map_err(|err| UrlRepoError::Replace(ReplaceUrlError::ClientError(err.into())))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UrlRepoError::Replace(ReplaceUrlError::NotFound(url.short)));
+        }
+
+        Ok(())
+    }
+
+    async fn find(&self, query: UrlQuery) -> Result<Option<Url>, UrlRepoError> {
+        let (condition, binds) = query.into_where_clause();
+
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {} LIMIT 1",
+            COLUMNS, self.table, condition
+        );
+        debug!("Find query: {}", sql);
+
+        let row = binds
+            .into_iter()
+            .fold(sqlx::query(&sql), |query, bind| query.bind(bind))
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|err| UrlRepoError::Get(GetUrlError::ClientError(err.into())))?;
+
+        match row {
+            Some(row) => url_from_row(&row)
+                .map(Some)
+                .map_err(|err| UrlRepoError::Get(GetUrlError::InternalError(err))),
+            None => Err(UrlRepoError::Get(GetUrlError::NotFound)),
+        }
+    }
+
+    #[instrument(skip(self), fields(short = %short))]
+    async fn delete(&self, short: &str) -> Result<Url, UrlRepoError> {
+        let row = sqlx::query(&format!(
+            "DELETE FROM {} WHERE short = $1 RETURNING {}",
+            self.table, COLUMNS
+        ))
+        .bind(short)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|err| UrlRepoError::Delete(DeleteUrlError::ClientError(err.into())))?
+        .ok_or_else(|| UrlRepoError::Delete(DeleteUrlError::NotFound(short.to_string())))?;
+
+        url_from_row(&row).map_err(|err| UrlRepoError::Delete(DeleteUrlError::ClientError(err)))
+    }
+
+    #[instrument(skip(self))]
+    async fn list_by_user(
+        &self,
+        user_id: &str,
+        page: PageRequest,
+    ) -> Result<Page<Url>, UrlRepoError> {
+        let after = page.start_after()?;
+
+        // Fetch one extra row to know whether another page follows.
+        let limit = i64::from(page.limit) + 1;
+        let condition = if after.is_some() {
+            "user_id = $1 AND short > $2"
+        } else {
+            "user_id = $1"
+        };
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {} ORDER BY short LIMIT {}",
+            COLUMNS, self.table, condition, limit
+        );
+
+        let mut query = sqlx::query(&sql).bind(user_id);
+        if let Some(after) = after {
+            query = query.bind(after);
+        }
+
+        let mut items = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|err| UrlRepoError::List(ListUrlsError::ClientError(err.into())))?
+            .iter()
+            .map(url_from_row)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| UrlRepoError::List(ListUrlsError::InternalError(err)))?;
+
+        let next_cursor = if items.len() > page.limit as usize {
+            items.truncate(page.limit as usize);
+            items.last().map(|url| encode_cursor(&url.short))
+        } else {
+            None
+        };
+
+        Ok(Page::builder()
+            .items(items)
+            .maybe_next_cursor(next_cursor)
+            .build())
+    }
+
+    #[instrument(skip(self))]
+    async fn count(&self) -> Result<u64, UrlRepoError> {
+        let row = sqlx::query(&format!("SELECT COUNT(*) AS count FROM {}", self.table))
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|err| UrlRepoError::Count(CountUrlsError::ClientError(err.into())))?;
+
+        count_from_row(&row)
+    }
+
+    #[instrument(skip(self))]
+    async fn count_by_user(&self, user_id: &str) -> Result<u64, UrlRepoError> {
+        let row = sqlx::query(&format!(
+            "SELECT COUNT(*) AS count FROM {} WHERE user_id = $1",
+            self.table
+        ))
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|err| UrlRepoError::Count(CountUrlsError::ClientError(err.into())))?;
+
+        count_from_row(&row)
+    }
+
+    /// Pages through the table by short code, so no connection is held between batches.
+    #[instrument(skip(self))]
+    async fn scan(
+        &self,
+        batch_size: u32,
+    ) -> Result<BoxStream<'static, Result<Url, UrlRepoError>>, UrlRepoError> {
+        let pool = self.pool.clone();
+        let sql = format!(
+            "SELECT {} FROM {} WHERE short > $1 ORDER BY short LIMIT {}",
+            COLUMNS, self.table, batch_size
+        );
+
+        let batches = futures_util::stream::try_unfold(Some(String::new()), move |after| {
+            let pool = pool.clone();
+            let sql = sql.clone();
+
+            async move {
+                let Some(after) = after else {
+                    return Ok::<_, UrlRepoError>(None);
+                };
+
+                let urls = sqlx::query(&sql)
+                    .bind(after)
+                    .fetch_all(&pool)
+                    .await
+                    .map_err(|err| UrlRepoError::List(ListUrlsError::ClientError(err.into())))?
+                    .iter()
+                    .map(url_from_row)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|err| UrlRepoError::List(ListUrlsError::InternalError(err)))?;
+
+                if urls.is_empty() {
+                    return Ok(None);
+                }
+
+                let next = (urls.len() == batch_size as usize)
+                    .then(|| urls.last().map(|url| url.short.clone()))
+                    .flatten();
+
+                Ok(Some((
+                    futures_util::stream::iter(urls.into_iter().map(Ok)),
+                    next,
+                )))
+            }
+        });
+
+        Ok(batches.try_flatten().boxed())
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_expired(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<Url>, UrlRepoError> {
+        // Expirations are stored as fixed-width UTC text, which sorts chronologically
+        let rows = sqlx::query(&format!(
+            "DELETE FROM {table} WHERE short IN (
+                SELECT short FROM {table} WHERE expiration_date <= $1 LIMIT {limit}
+            ) RETURNING {columns}",
+            table = self.table,
+            limit = limit,
+            columns = COLUMNS
+        ))
+        .bind(Url::format_expiration_date(&now))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| UrlRepoError::Delete(DeleteUrlError::ClientError(err.into())))?;
+
+        rows.iter()
+            .map(url_from_row)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| UrlRepoError::Delete(DeleteUrlError::ClientError(err)))
+    }
+}
+
+impl SqlUrlRepo {
+    #[instrument(skip(config))]
+    pub async fn new(config: SqlConfig) -> Result<Self, SqlUrlRepoError> {
+        let dialect = config
+            .dialect()
+            .ok_or_else(|| SqlUrlRepoError::UnsupportedUrl(config.url.clone()))?;
+        sqlx::any::install_default_drivers();
+
+        // Every connection to `sqlite::memory:` opens its own database, so keep exactly one alive.
+        let options = if config.is_in_memory() {
+            AnyPoolOptions::new()
+                .max_connections(1)
+                .min_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            AnyPoolOptions::new().max_connections(config.max_connections.unwrap_or(10))
+        };
+        let pool = options.connect(&config.url).await?;
+        info!("Connected to {:?} database", dialect);
+
+        let table = config.tables["url_repo"].clone();
+        info!("Creating table if not exists: {}", table);
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                long            TEXT NOT NULL,
+                short           TEXT NOT NULL,
+                alias           TEXT,
+                expiration_date TEXT,
+                created_at      TEXT NOT NULL,
+                updated_at      TEXT NOT NULL,
+                user_id         TEXT NOT NULL,
+                fallback_url    TEXT
+            )",
+            table
+        ))
+        .execute(&pool)
+        .await?;
+
+        // Tables created before fallback URLs existed lack the column
+        let has_fallback_url = sqlx::query(&format!("SELECT fallback_url FROM {} LIMIT 0", table))
+            .execute(&pool)
+            .await
+            .is_ok();
+        if !has_fallback_url {
+            info!("Adding column fallback_url to table: {}", table);
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN fallback_url TEXT",
+                table
+            ))
+            .execute(&pool)
+            .await?;
+        }
+
+        Ok(SqlUrlRepo {
+            config,
+            dialect,
+            table,
+            pool,
+        })
+    }
+
+    /// Creates the indexes declared by `UrlIndexes` and drops indexes on the same keys
+    /// whose uniqueness, sparseness or partial filter no longer matches.
+    ///
+    /// Unlike MongoDB, SQL treats every `NULL` as distinct, so a unique index never
+    /// rejects two rows whose indexed columns are all `NULL`.
+    #[instrument(skip(self))]
+    pub async fn ensure_indexes(&self) -> Result<(), SqlUrlRepoError> {
+        let url_indexes = UrlIndexes::default();
+        info!("Ensuring indexes for table: {}", self.table);
+        info!("Indexes: {:#?}", url_indexes);
+        url_indexes.validate()?;
+
+        let existing_indexes = self.existing_index_names().await?;
+        info!("SQL existing indexes: {:#?}", existing_indexes);
+
+        for new in url_indexes.values {
+            let name = self.index_name(&new);
+            let prefix = self.index_prefix(&new);
+
+            for stale in existing_indexes
+                .iter()
+                .filter(|existing| existing.starts_with(&prefix) && **existing != name)
+            {
+                info!("Dropping index: {}", stale);
+                sqlx::query(&format!("DROP INDEX IF EXISTS {}", stale))
+                    .execute(&self.pool)
+                    .await?;
+            }
+
+            if existing_indexes.contains(&name) {
+                continue;
+            }
+
+            let sql = self.create_index_sql(&new);
+            info!("Creating index: {}", sql);
+            sqlx::query(&sql).execute(&self.pool).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn existing_index_names(&self) -> Result<Vec<String>, SqlUrlRepoError> {
+        let sql = match self.dialect {
+            SqlDialect::Postgres => {
+                "SELECT indexname::TEXT AS name FROM pg_indexes WHERE tablename = $1"
+            }
+            SqlDialect::Sqlite => {
+                "SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = $1"
+            }
+        };
+
+        let rows = sqlx::query(sql)
+            .bind(self.table.clone())
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| row.try_get::<String, _>("name"))
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    fn index_prefix(&self, index: &UrlIndex) -> String {
+        let columns = index.keys.iter().map(|key| column(key)).collect::<Vec<_>>();
+
+        format!("{}__{}__", self.table, columns.join("_"))
+    }
+
+    /// Index names encode their options, so a changed option is detected as a different name.
+    fn index_name(&self, index: &UrlIndex) -> String {
+        let kind = if index.is_unique { "uniq" } else { "idx" };
+        let sparse = if index.is_sparse { "_sparse" } else { "" };
+        let partial = match index.partial_filter.as_ref() {
+            Some(PartialFilter::IsString(fields)) => format!(
+                "_where_{}",
+                fields
+                    .iter()
+                    .map(|field| column(field))
+                    .collect::<Vec<_>>()
+                    .join("_")
+            ),
+            None => String::new(),
+        };
+
+        format!("{}{}{}{}", self.index_prefix(index), kind, sparse, partial)
+    }
+
+    pub fn create_index_sql(&self, index: &UrlIndex) -> String {
+        let columns = index.keys.iter().map(|key| column(key)).collect::<Vec<_>>();
+
+        let mut sql = format!(
+            "CREATE {}INDEX IF NOT EXISTS {} ON {} ({})",
+            if index.is_unique { "UNIQUE " } else { "" },
+            self.index_name(index),
+            self.table,
+            columns.join(", ")
+        );
+
+        let mut conditions = Vec::new();
+
+        if index.is_sparse {
+            let any_present = columns
+                .iter()
+                .map(|column| format!("{} IS NOT NULL", column))
+                .collect::<Vec<_>>();
+            conditions.push(format!("({})", any_present.join(" OR ")));
+        }
+
+        // Every column is TEXT, so "holds a string" is "is not NULL"
+        if let Some(PartialFilter::IsString(fields)) = index.partial_filter.as_ref() {
+            conditions.extend(
+                fields
+                    .iter()
+                    .map(|field| format!("{} IS NOT NULL", column(field))),
+            );
+        }
+
+        if !conditions.is_empty() {
+            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+
+        sql
+    }
+}
+
+/// Maps a serialized field name such as `userId` to its column, `user_id`.
+fn column(key: &str) -> String {
+    key.chars().fold(String::new(), |mut column, c| {
+        if c.is_ascii_uppercase() {
+            column.push('_');
+            column.push(c.to_ascii_lowercase());
+        } else {
+            column.push(c);
+        }
+        column
+    })
+}
+
+pub trait IntoWhereClause {
+    /// Returns a SQL condition with `$n` placeholders and the values to bind to them.
+    fn into_where_clause(self) -> (String, Vec<String>);
+}
+
+impl IntoWhereClause for UrlQuery {
+    fn into_where_clause(self) -> (String, Vec<String>) {
+        match self {
+            UrlQuery::ByShort(short) => ("short = $1".to_string(), vec![short]),
+            UrlQuery::ByAlias(alias) => ("alias = $1".to_string(), vec![alias]),
+            UrlQuery::ByShortOrAlias(code) => (
+                "(alias = $1 OR short = $2)".to_string(),
+                vec![code.clone(), code],
+            ),
+            UrlQuery::ByUserAndLong { user_id, long } => (
+                "user_id = $1 AND long = $2".to_string(),
+                vec![user_id, long],
+            ),
+        }
+    }
+}
+
+fn count_from_row(row: &AnyRow) -> Result<u64, UrlRepoError> {
+    row.try_get::<i64, _>("count")
+        .map(|count| count as u64)
+        .map_err(|err| UrlRepoError::Count(CountUrlsError::ClientError(err.into())))
+}
+
+fn url_from_row(row: &AnyRow) -> Result<Url, anyhow::Error> {
+    let expiration_date = row
+        .try_get::<Option<String>, _>("expiration_date")?
+        .map(|date| Url::parse_expiration_date(&date))
+        .transpose()?;
+
+    Ok(Url::builder()
+        .long(row.try_get("long")?)
+        .short(row.try_get("short")?)
+        .alias(row.try_get::<Option<String>, _>("alias")?)
+        .expiration_date(expiration_date)
+        .created_at(NaiveDateTime::parse_from_str(
+            &row.try_get::<String, _>("created_at")?,
+            DATE_TIME_FORMAT,
+        )?)
+        .updated_at(NaiveDateTime::parse_from_str(
+            &row.try_get::<String, _>("updated_at")?,
+            DATE_TIME_FORMAT,
+        )?)
+        .user_id(row.try_get("user_id")?)
+        .maybe_fallback_url(row.try_get::<Option<String>, _>("fallback_url")?)
+        .build())
+}
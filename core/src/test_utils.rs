@@ -0,0 +1,26 @@
+use chrono::{DateTime, Utc};
+
+use crate::domain::entities::url::Url;
+
+/// A URL owned by `test_user` that points to `https://example.com/{short}`, with the
+/// other fields only set where a test cares about them.
+#[builder]
+pub fn url(
+    #[builder(start_fn)] short: &str,
+    #[builder(into)] long: Option<String>,
+    #[builder(into)] alias: Option<String>,
+    expiration_date: Option<DateTime<Utc>>,
+    #[builder(into)] fallback_url: Option<String>,
+    #[builder(default = "test_user".to_string(), into)] user_id: String,
+) -> Url {
+    Url::builder()
+        .long(long.unwrap_or_else(|| format!("https://example.com/{}", short)))
+        .short(short.to_string())
+        .alias(alias)
+        .expiration_date(expiration_date)
+        .maybe_fallback_url(fallback_url)
+        .user_id(user_id)
+        .created_at(Utc::now().naive_utc())
+        .updated_at(Utc::now().naive_utc())
+        .build()
+}
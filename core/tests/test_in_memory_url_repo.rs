@@ -0,0 +1,231 @@
+mod utils;
+
+use futures_util::TryStreamExt;
+use pretty_assertions::assert_eq;
+use utils::init_tracing;
+use wee_core::{
+    domain::repos::{
+        page::{PageRequest, encode_cursor},
+        url_query::UrlQuery,
+        url_repo::{
+            DeleteUrlError, GetUrlError, InsertUrlError, ListUrlsError, ReplaceUrlError, UrlRepo,
+            UrlRepoError,
+        },
+    },
+    outbound::in_memory::url_repo::InMemoryUrlRepo,
+    test_utils::url,
+};
+
+#[tokio::test]
+async fn test_insert_and_get() {
+    init_tracing();
+    let repo = InMemoryUrlRepo::default();
+    let url = url("a").long("https://example.com").alias("example").call();
+
+    repo.insert(url.clone()).await.unwrap();
+
+    assert_eq!(repo.get("a").await.unwrap(), url);
+    assert!(matches!(
+        repo.get("b").await,
+        Err(UrlRepoError::Get(GetUrlError::NotFound))
+    ));
+}
+
+#[tokio::test]
+async fn test_insert_duplicate_short() {
+    init_tracing();
+    let repo = InMemoryUrlRepo::default();
+
+    repo.insert(url("a").long("https://example.com").alias("one").call())
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        repo.insert(url("a").long("https://example.org").alias("two").call())
+            .await,
+        Err(UrlRepoError::Insert(InsertUrlError::AlreadyExists))
+    ));
+}
+
+#[tokio::test]
+async fn test_replace_if_exists() {
+    init_tracing();
+    let repo = InMemoryUrlRepo::default();
+
+    repo.insert(url("a").long("https://example.com").alias("one").call())
+        .await
+        .unwrap();
+
+    let replacement = url("a").long("https://example.org").alias("one").call();
+    repo.replace_if_exists(replacement.clone()).await.unwrap();
+    assert_eq!(repo.get("a").await.unwrap(), replacement);
+
+    assert!(matches!(
+        repo.replace_if_exists(url("b").long("https://example.net").call())
+            .await,
+        Err(UrlRepoError::Replace(ReplaceUrlError::NotFound(short))) if short == "b"
+    ));
+}
+
+#[tokio::test]
+async fn test_find() {
+    init_tracing();
+    let repo = InMemoryUrlRepo::default();
+    let url = url("a").long("https://example.com").alias("example").call();
+
+    repo.insert(url.clone()).await.unwrap();
+
+    let found = repo
+        .find(UrlQuery::ByShortOrAlias("example".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(found, Some(url));
+
+    assert!(matches!(
+        repo.find(UrlQuery::ByShort("missing".to_string())).await,
+        Err(UrlRepoError::Get(GetUrlError::NotFound))
+    ));
+}
+
+#[tokio::test]
+async fn test_lifecycle() {
+    init_tracing();
+    let repo = InMemoryUrlRepo::default();
+
+    for short in ["c", "a", "b"] {
+        repo.insert(url(short).alias(format!("alias-{}", short)).call())
+            .await
+            .unwrap();
+    }
+    repo.insert(url("d").alias("alias-d").user_id("other_user").call())
+        .await
+        .unwrap();
+
+    assert_eq!(repo.count().await.unwrap(), 4);
+    assert_eq!(repo.count_by_user("test_user").await.unwrap(), 3);
+
+    let first = repo
+        .list_by_user("test_user", PageRequest::builder().limit(2).build())
+        .await
+        .unwrap();
+    assert_eq!(
+        first
+            .items
+            .iter()
+            .map(|url| url.short.as_str())
+            .collect::<Vec<_>>(),
+        vec!["a", "b"]
+    );
+    assert_eq!(first.next_cursor, Some(encode_cursor("b")));
+
+    let second = repo
+        .list_by_user(
+            "test_user",
+            PageRequest::builder()
+                .limit(2)
+                .maybe_after(first.next_cursor)
+                .build(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        second
+            .items
+            .iter()
+            .map(|url| url.short.as_str())
+            .collect::<Vec<_>>(),
+        vec!["c"]
+    );
+    assert_eq!(second.next_cursor, None);
+
+    let mut scanned = repo
+        .scan(2)
+        .await
+        .unwrap()
+        .map_ok(|url| url.short)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    scanned.sort();
+    assert_eq!(scanned, vec!["a", "b", "c", "d"]);
+
+    assert_eq!(repo.delete("a").await.unwrap().short, "a");
+    assert!(matches!(
+        repo.delete("a").await,
+        Err(UrlRepoError::Delete(DeleteUrlError::NotFound(short))) if short == "a"
+    ));
+    assert_eq!(repo.count().await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn test_list_invalid_page() {
+    init_tracing();
+    let repo = InMemoryUrlRepo::default();
+
+    for page in [
+        PageRequest::builder().limit(0).build(),
+        PageRequest::builder().after("").build(),
+        PageRequest::builder().after("not a cursor").build(),
+    ] {
+        assert!(matches!(
+            repo.list_by_user("test_user", page).await,
+            Err(UrlRepoError::List(
+                ListUrlsError::InvalidLimit(_) | ListUrlsError::InvalidCursor(_)
+            ))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_insert_many_without_alias() {
+    init_tracing();
+    let repo = InMemoryUrlRepo::default();
+
+    repo.insert(url("a").long("https://example.com").call())
+        .await
+        .unwrap();
+    repo.insert(url("b").long("https://example.org").call())
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        repo.insert(url("c").long("https://example.net").alias("taken").call())
+            .await,
+        Ok(())
+    ));
+    assert!(matches!(
+        repo.insert(url("d").long("https://example.io").alias("taken").call())
+            .await,
+        Err(UrlRepoError::Insert(InsertUrlError::AlreadyExists))
+    ));
+}
+
+#[tokio::test]
+async fn test_delete_expired() {
+    init_tracing();
+    let repo = InMemoryUrlRepo::default();
+    let now = "2030-01-01T12:00:00Z"
+        .parse::<chrono::DateTime<chrono::Utc>>()
+        .unwrap();
+
+    for (short, expiration_date) in [
+        ("a", Some(now - chrono::Duration::seconds(1))),
+        ("b", Some(now)),
+        ("c", Some(now + chrono::Duration::seconds(1))),
+        ("d", None),
+    ] {
+        repo.insert(
+            url(short)
+                .alias(format!("alias-{}", short))
+                .maybe_expiration_date(expiration_date)
+                .call(),
+        )
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(repo.delete_expired(now, 1).await.unwrap().len(), 1);
+    assert_eq!(repo.delete_expired(now, 10).await.unwrap().len(), 1);
+    assert!(repo.delete_expired(now, 10).await.unwrap().is_empty());
+    assert_eq!(repo.count().await.unwrap(), 2);
+}
@@ -2,12 +2,20 @@ mod utils;
 
 use futures_util::TryStreamExt;
 use map_macro::hash_map;
-use mongodb::Client;
+use mongodb::{Client, IndexModel, bson::doc, options::IndexOptions};
+use pretty_assertions::assert_eq;
 use tracing::{debug, error};
 use utils::init_tracing;
-use wee_core::outbound::mongodb::{MongoConfig, url_repo::MongoUrlRepo};
+use wee_core::{
+    domain::repos::{
+        page::{PageRequest, encode_cursor},
+        url_repo::{DeleteUrlError, UrlRepo, UrlRepoError},
+    },
+    outbound::mongodb::{MongoConfig, url_repo::MongoUrlRepo},
+    test_utils::url,
+};
 
-async fn set_up() -> MongoUrlRepo {
+async fn set_up(collection: &str) -> MongoUrlRepo {
     init_tracing();
 
     let config = MongoConfig::builder()
@@ -17,7 +25,7 @@ async fn set_up() -> MongoUrlRepo {
         .username("test")
         .password("test")
         .collections(hash_map! {
-            "url_repo".to_string() => "collection-test-{}".to_string(),
+            "url_repo".to_string() => format!("collection-test-{}", collection),
         })
         .build();
 
@@ -48,9 +56,39 @@ async fn tear_down(mongo_url_repo: MongoUrlRepo) {
 
 #[tokio::test]
 async fn test_ensure_indexes() {
-    let mongo_url_repo = set_up().await;
-    mongo_url_repo.ensure_indexes().await.unwrap();
+    let mongo_url_repo = set_up("ensure-indexes").await;
+    for (keys, name) in [
+        (doc! { "user_id": 1, "long": 1 }, "user_id_1_long_1"),
+        (doc! { "clicks": 1 }, "clicks_1"),
+        (doc! { "short": 1 }, "short_1"),
+    ] {
+        mongo_url_repo
+            .collection
+            .create_index(
+                IndexModel::builder()
+                    .keys(keys)
+                    .options(IndexOptions::builder().name(name.to_string()).build())
+                    .build(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let plan = mongo_url_repo.plan_indexes().await.unwrap();
+    assert_eq!(plan.create.len(), 3);
+    // The legacy index goes, and `short_1` is rebuilt since it isn't unique
+    assert_eq!(
+        plan.drop,
+        vec!["user_id_1_long_1".to_string(), "short_1".to_string()]
+    );
+    mongo_url_repo.apply_index_plan(&plan).await.unwrap();
+
+    // Once applied, there is nothing left to do
+    let plan = mongo_url_repo.plan_indexes().await.unwrap();
+    assert!(plan.is_empty());
+    assert_eq!(plan.keep.len(), 3);
 
+    // Only the index an earlier version created goes
     let existing_indexes = mongo_url_repo
         .collection
         .list_indexes()
@@ -60,6 +98,111 @@ async fn test_ensure_indexes() {
         .await
         .unwrap();
     debug!("Existing indexes after test: {:#?}", existing_indexes);
+    let names = existing_indexes
+        .iter()
+        .filter_map(|index| index.options.as_ref()?.name.clone())
+        .collect::<Vec<_>>();
+    assert!(names.contains(&"clicks_1".to_string()));
+    assert!(!names.contains(&"user_id_1_long_1".to_string()));
+
+    tear_down(mongo_url_repo).await;
+}
+
+#[tokio::test]
+async fn test_lifecycle() {
+    let mongo_url_repo = set_up("lifecycle").await;
+
+    for short in ["c", "a", "b"] {
+        mongo_url_repo
+            .insert(url(short).alias(format!("alias-{}", short)).call())
+            .await
+            .unwrap();
+    }
+    mongo_url_repo
+        .insert(url("d").alias("alias-d").user_id("other_user").call())
+        .await
+        .unwrap();
+
+    assert_eq!(mongo_url_repo.count().await.unwrap(), 4);
+    assert_eq!(mongo_url_repo.count_by_user("test_user").await.unwrap(), 3);
+
+    let first = mongo_url_repo
+        .list_by_user("test_user", PageRequest::builder().limit(2).build())
+        .await
+        .unwrap();
+    assert_eq!(first.items.len(), 2);
+    assert_eq!(first.next_cursor, Some(encode_cursor("b")));
+
+    let second = mongo_url_repo
+        .list_by_user(
+            "test_user",
+            PageRequest::builder()
+                .limit(2)
+                .maybe_after(first.next_cursor)
+                .build(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(second.items.len(), 1);
+    assert_eq!(second.next_cursor, None);
+
+    let scanned = mongo_url_repo
+        .scan(2)
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(scanned.len(), 4);
+
+    mongo_url_repo.delete("a").await.unwrap();
+    assert!(matches!(
+        mongo_url_repo.delete("a").await,
+        Err(UrlRepoError::Delete(DeleteUrlError::NotFound(_)))
+    ));
+
+    tear_down(mongo_url_repo).await;
+}
+
+#[tokio::test]
+async fn test_insert_many_without_alias() {
+    let mongo_url_repo = set_up("without-alias").await;
+    mongo_url_repo.ensure_indexes().await.unwrap();
+
+    for short in ["a", "b"] {
+        mongo_url_repo.insert(url(short).call()).await.unwrap();
+    }
+
+    tear_down(mongo_url_repo).await;
+}
+
+#[tokio::test]
+async fn test_migrate_expiration_dates() {
+    let mongo_url_repo = set_up("migrate-expirations").await;
+
+    let mut legacy = mongodb::bson::to_document(&url("a").alias("alias-a").call()).unwrap();
+    legacy.insert("expirationDate", "2030-01-01");
+    mongo_url_repo
+        .collection
+        .clone_with_type::<mongodb::bson::Document>()
+        .insert_one(legacy)
+        .await
+        .unwrap();
+
+    assert_eq!(mongo_url_repo.migrate_expiration_dates().await.unwrap(), 1);
+    assert_eq!(mongo_url_repo.migrate_expiration_dates().await.unwrap(), 0);
+
+    let stored = mongo_url_repo
+        .collection
+        .clone_with_type::<mongodb::bson::Document>()
+        .find_one(mongodb::bson::doc! {"short": "a"})
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        stored.get_str("expirationDate").unwrap(),
+        "2030-01-02T00:00:00Z"
+    );
 
     tear_down(mongo_url_repo).await;
 }
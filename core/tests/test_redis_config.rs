@@ -0,0 +1,178 @@
+use std::num::NonZeroU64;
+
+use chrono::{DateTime, Duration, Utc};
+use map_macro::hash_map;
+use pretty_assertions::assert_eq;
+use redis::ConnectionAddr;
+use wee_core::{
+    outbound::redis::{RedisConfig, RedisNode, RedisTopology, connection::RedisConnectionError},
+    test_utils::url,
+};
+
+#[test]
+fn test_ttl_secs() {
+    let config = RedisConfig::builder()
+        .host("localhost")
+        .port(6379)
+        .dbs(hash_map! {})
+        .namespace("wee")
+        .max_ttl_secs(NonZeroU64::new(3_600).unwrap())
+        .build();
+    let now = "2030-01-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+    assert_eq!(config.ttl_secs(&url("a").call(), now), Some(3_600));
+    assert_eq!(
+        config.ttl_secs(
+            &url("a").expiration_date(now + Duration::minutes(10)).call(),
+            now
+        ),
+        Some(600)
+    );
+    assert_eq!(
+        config.ttl_secs(
+            &url("a").expiration_date(now + Duration::days(2)).call(),
+            now
+        ),
+        Some(3_600)
+    );
+    assert_eq!(
+        config.ttl_secs(&url("a").expiration_date(now).call(), now),
+        None
+    );
+    assert_eq!(
+        config.ttl_secs(
+            &url("a").expiration_date(now - Duration::seconds(1)).call(),
+            now
+        ),
+        None
+    );
+}
+
+#[test]
+fn test_connection_info() {
+    let config = RedisConfig::builder()
+        .host("localhost")
+        .port(6379)
+        .dbs(hash_map! {})
+        .namespace("wee")
+        .max_ttl_secs(NonZeroU64::new(3_600).unwrap())
+        .build();
+
+    let info = config.connection_info("localhost", 6379, 3);
+    assert_eq!(
+        info.addr,
+        ConnectionAddr::Tcp("localhost".to_string(), 6379)
+    );
+    assert_eq!(info.redis.db, 3);
+    assert_eq!(info.redis.username, None);
+    assert_eq!(info.redis.password, None);
+
+    let config = RedisConfig {
+        username: Some("wee".to_string()),
+        password: Some("secret".to_string()),
+        tls: true,
+        ..config
+    };
+
+    let info = config.connection_info("redis.internal", 6380, 0);
+    assert_eq!(
+        info.addr,
+        ConnectionAddr::TcpTls {
+            host: "redis.internal".to_string(),
+            port: 6380,
+            insecure: false,
+            tls_params: None,
+        }
+    );
+    assert_eq!(info.redis.username.as_deref(), Some("wee"));
+    assert_eq!(info.redis.password.as_deref(), Some("secret"));
+}
+
+#[test]
+fn test_topology() {
+    let config: RedisConfig = serde_json::from_value(serde_json::json!({
+        "host": "localhost",
+        "port": 6379,
+        "dbs": {},
+        "namespace": "wee",
+        "max_ttl_secs": 3600,
+    }))
+    .unwrap();
+    assert_eq!(config.topology, RedisTopology::Standalone);
+    assert!(!config.tls);
+
+    let topology: RedisTopology = serde_json::from_value(serde_json::json!({
+        "mode": "sentinel",
+        "master_name": "wee",
+        "nodes": [{ "host": "sentinel-1", "port": 26379 }],
+    }))
+    .unwrap();
+    assert_eq!(
+        topology,
+        RedisTopology::Sentinel {
+            master_name: "wee".to_string(),
+            nodes: vec![RedisNode {
+                host: "sentinel-1".to_string(),
+                port: 26379,
+            }],
+            password: None,
+        }
+    );
+
+    let topology: RedisTopology = serde_json::from_value(serde_json::json!({
+        "mode": "cluster",
+        "nodes": [{ "host": "redis-1", "port": 6379 }],
+    }))
+    .unwrap();
+    assert_eq!(
+        topology,
+        RedisTopology::Cluster {
+            nodes: vec![RedisNode {
+                host: "redis-1".to_string(),
+                port: 6379,
+            }],
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_cluster_only_has_db_0() {
+    let config = RedisConfig::builder()
+        .host("localhost")
+        .port(6379)
+        .dbs(hash_map! {
+            "url_cache".to_string() => 15,
+        })
+        .namespace("wee")
+        .max_ttl_secs(NonZeroU64::new(3_600).unwrap())
+        .topology(RedisTopology::Cluster {
+            nodes: vec![RedisNode {
+                host: "localhost".to_string(),
+                port: 6379,
+            }],
+        })
+        .build();
+
+    assert!(matches!(
+        config.connect("url_cache").await,
+        Err(RedisConnectionError::ClusterDb(db, 15)) if db == "url_cache"
+    ));
+    assert!(matches!(
+        config.connect("shorten").await,
+        Err(RedisConnectionError::MissingDb(db)) if db == "shorten"
+    ));
+}
+
+#[test]
+fn test_reject_zero_max_ttl() {
+    assert!(
+        serde_json::from_value::<RedisConfig>(serde_json::json!({
+            "host": "localhost",
+            "port": 6379,
+            "dbs": {},
+            "namespace": "wee",
+            "max_ttl_secs": 0,
+        }))
+        .is_err()
+    );
+}
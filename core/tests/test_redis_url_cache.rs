@@ -0,0 +1,117 @@
+mod utils;
+
+use std::num::NonZeroU64;
+
+use chrono::Utc;
+use futures_util::StreamExt;
+use map_macro::hash_map;
+use pretty_assertions::assert_eq;
+use redis::AsyncCommands;
+use utils::init_tracing;
+use wee_core::{
+    outbound::redis::{
+        RedisConfig,
+        connection::RedisConnectionError,
+        url_cache::{RedisUrlCache, RedisUrlCacheError, UrlCacheKeys},
+    },
+    test_utils::url,
+};
+
+fn config(namespace: &str) -> RedisConfig {
+    RedisConfig::builder()
+        .host("localhost")
+        .port(6379)
+        .dbs(hash_map! {
+            "url_cache".to_string() => 15,
+        })
+        .namespace(namespace)
+        .max_ttl_secs(NonZeroU64::new(3_600).unwrap())
+        .build()
+}
+
+#[test]
+fn test_keys() {
+    let keys = UrlCacheKeys {
+        namespace: "wee".to_string(),
+        hash_tags: false,
+    };
+
+    assert_eq!(keys.short("abc"), "wee:short:abc");
+    assert_eq!(keys.alias("promo"), "wee:alias:promo");
+    assert_eq!(keys.user_urls("user"), "wee:user:user:urls");
+    assert_eq!(
+        keys.codes(&url("abc").alias("promo").call()),
+        vec!["wee:short:abc", "wee:alias:promo"]
+    );
+    assert_eq!(keys.codes(&url("abc").call()), vec!["wee:short:abc"]);
+
+    let keys = UrlCacheKeys {
+        hash_tags: true,
+        ..keys
+    };
+
+    assert_eq!(keys.short("abc"), "wee:short:{abc}");
+    assert_eq!(keys.alias("abc"), "wee:alias:{abc}");
+    assert_eq!(keys.user_urls("user"), "wee:user:{user}:urls");
+}
+
+#[tokio::test]
+async fn test_missing_db() {
+    let mut config = config("wee-test-missing");
+    config.dbs.clear();
+
+    assert!(matches!(
+        RedisUrlCache::new(config).await,
+        Err(RedisUrlCacheError::ConnectionError(RedisConnectionError::MissingDb(db))) if db == "url_cache"
+    ));
+}
+
+#[tokio::test]
+async fn test_set_get_and_evict() {
+    init_tracing();
+    let namespace = format!("wee-test-{}", Utc::now().timestamp_nanos_opt().unwrap());
+    let cache = RedisUrlCache::new(config(&namespace)).await.unwrap();
+    let url = url("abc").alias("promo").call();
+
+    cache.set(&url).await.unwrap();
+
+    assert_eq!(cache.get_by_short("abc").await.unwrap(), Some(url.clone()));
+    assert_eq!(
+        cache.get_by_alias("promo").await.unwrap(),
+        Some(url.clone())
+    );
+    assert_eq!(cache.get_by_code("promo").await.unwrap(), Some(url.clone()));
+    assert_eq!(
+        cache
+            .get_by_long_url("https://example.com/abc", "test_user")
+            .await
+            .unwrap(),
+        Some(url.clone())
+    );
+
+    // Everything lives under the namespace
+    let keys: Vec<String> = cache
+        .conn
+        .clone()
+        .keys(format!("{}:*", namespace))
+        .await
+        .unwrap();
+    assert_eq!(keys.len(), 3);
+
+    let mut evictions = Box::pin(cache.evictions().await.unwrap());
+    cache.evict(&url).await.unwrap();
+    assert_eq!(
+        evictions.next().await,
+        Some(vec!["abc".to_string(), "promo".to_string()])
+    );
+
+    assert_eq!(cache.get_by_code("abc").await.unwrap(), None);
+    assert_eq!(cache.get_by_code("promo").await.unwrap(), None);
+    assert_eq!(
+        cache
+            .get_by_long_url("https://example.com/abc", "test_user")
+            .await
+            .unwrap(),
+        None
+    );
+}
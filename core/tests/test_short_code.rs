@@ -0,0 +1,325 @@
+use std::collections::HashSet;
+
+use pretty_assertions::assert_eq;
+use wee_core::domain::short_code::{
+    ADJECTIVES, DEFAULT_ALPHABET, NOUNS, ShortCodeConfig, ShortCodeEncoder, ShortCodeError,
+};
+
+fn encoder(key: u64) -> ShortCodeEncoder {
+    ShortCodeEncoder::new(ShortCodeConfig::builder().key(key).build()).unwrap()
+}
+
+#[test]
+fn test_codes_are_unique_and_not_sequential() {
+    let encoder = encoder(42);
+
+    let codes = (0..100_000)
+        .map(|id| encoder.encode(id))
+        .collect::<Vec<_>>();
+
+    assert_eq!(codes.iter().collect::<HashSet<_>>().len(), codes.len());
+    assert!(codes.iter().all(|code| code.len() <= 6));
+    assert!((0..99_999).all(|id| encoder.scramble(id) + 1 != encoder.scramble(id + 1)));
+}
+
+#[test]
+fn test_decode_reverses_encode() {
+    let encoder = encoder(42);
+
+    for id in [
+        0,
+        1,
+        61,
+        62,
+        1 << 31,
+        u32::MAX as u64,
+        1 << 32,
+        1 << 40,
+        1 << 63,
+        u64::MAX,
+    ]
+    .into_iter()
+    .chain((1..64).map(|bits| (1u64 << bits) - 1))
+    {
+        let code = encoder.encode(id);
+        assert_eq!(
+            encoder.decode(&code),
+            Some(id),
+            "code {} of id {}",
+            code,
+            id
+        );
+    }
+
+    assert_eq!(encoder.decode("not-base62"), None);
+    assert_eq!(encoder.decode("zzzzzzzzzzzzzzzzzzzz"), None);
+}
+
+#[test]
+fn test_codes_keep_ids_size_class() {
+    let encoder = encoder(42);
+
+    for bits in 33..=64 {
+        let id = 1u64 << (bits - 1);
+        let scrambled = encoder.scramble(id);
+        let class_bits = bits + (bits & 1);
+
+        assert!(scrambled >= 1 << (class_bits - 2));
+        assert!(class_bits == 64 || scrambled < 1 << class_bits);
+    }
+}
+
+#[test]
+fn test_key_changes_codes() {
+    let first = encoder(1);
+    let second = encoder(2);
+
+    let same = (0..1_000)
+        .filter(|&id| first.encode(id) == second.encode(id))
+        .count();
+
+    assert!(same < 10);
+}
+
+#[test]
+fn test_custom_alphabet_and_min_length() {
+    let alphabet = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    let encoder = ShortCodeEncoder::new(
+        ShortCodeConfig::builder()
+            .key(42)
+            .alphabet(alphabet)
+            .min_length(8)
+            .build(),
+    )
+    .unwrap();
+
+    for id in (0..10_000).chain([u32::MAX as u64, u64::MAX]) {
+        let code = encoder.encode(id);
+
+        assert!(code.len() >= 8);
+        assert!(code.chars().all(|c| alphabet.contains(c)));
+        assert_eq!(encoder.decode(&code), Some(id));
+    }
+
+    // Too short, extra padding or characters outside the alphabet
+    assert_eq!(encoder.decode("1234567"), None);
+    assert_eq!(encoder.decode(&format!("0{}", encoder.encode(1))), None);
+    assert_eq!(encoder.decode("0000000O"), None);
+}
+
+#[test]
+fn test_invalid_config() {
+    let config = |alphabet: &str, min_length| {
+        ShortCodeConfig::builder()
+            .key(42)
+            .alphabet(alphabet)
+            .min_length(min_length)
+            .build()
+    };
+
+    assert!(matches!(
+        ShortCodeEncoder::new(config("a", 1)),
+        Err(ShortCodeError::AlphabetTooShort(_))
+    ));
+    assert!(matches!(
+        ShortCodeEncoder::new(config("abca", 1)),
+        Err(ShortCodeError::DuplicateCharacter('a'))
+    ));
+    assert!(matches!(
+        ShortCodeEncoder::new(config(DEFAULT_ALPHABET, 0)),
+        Err(ShortCodeError::InvalidMinLength)
+    ));
+}
+
+fn check_encoder(alphabet: &str) -> ShortCodeEncoder {
+    ShortCodeEncoder::new(
+        ShortCodeConfig::builder()
+            .key(42)
+            .alphabet(alphabet)
+            .min_length(6)
+            .check_character(true)
+            .build(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_check_character_catches_single_typos() {
+    // Both an even and an odd alphabet size
+    for alphabet in [DEFAULT_ALPHABET, "23456789ABCDEFGHJKMNPQRSTVWXYZ_"] {
+        let encoder = check_encoder(alphabet);
+        let alphabet = alphabet.chars().collect::<Vec<_>>();
+
+        for id in [0, 1, 12_345, u32::MAX as u64, u64::MAX] {
+            let code = encoder.encode(id);
+            assert!(code.chars().count() >= 7);
+            assert_eq!(encoder.decode(&code), Some(id));
+
+            let chars = code.chars().collect::<Vec<_>>();
+            for position in 0..chars.len() {
+                for &replacement in alphabet.iter().filter(|&&c| c != chars[position]) {
+                    let mut typo = chars.clone();
+                    typo[position] = replacement;
+                    let typo = typo.into_iter().collect::<String>();
+
+                    assert_eq!(
+                        encoder.decode(&typo),
+                        None,
+                        "{} accepted for {}",
+                        typo,
+                        code
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_suggest_code() {
+    let encoder = check_encoder("0123456789ABCDEFGHJKMNPQRSTVWXYZ");
+    let replace = |code: &str, position: usize, c: char| {
+        let mut chars = code.chars().collect::<Vec<_>>();
+        chars[position] = c;
+        chars.into_iter().collect::<String>()
+    };
+
+    let code = encoder.encode(12_345);
+    assert_eq!(encoder.suggest(&code), None);
+    // A character outside the alphabet pins down the typo
+    assert_eq!(encoder.suggest(&replace(&code, 2, 'U')), Some(code.clone()));
+    assert_eq!(encoder.suggest("UUUUUUU"), None);
+
+    // Otherwise the only look-alike correction wins, which is nearly always right
+    let (mut right, mut total) = (0, 0);
+    for id in 0..1_000 {
+        let code = encoder.encode(id);
+        let Some(position) = code.find('5') else {
+            continue;
+        };
+
+        let suggestion = encoder.suggest(&replace(&code, position, 'S'));
+        assert!(suggestion.iter().all(|code| encoder.decode(code).is_some()));
+        right += usize::from(suggestion == Some(code));
+        total += 1;
+    }
+    assert!(
+        right * 10 >= total * 9,
+        "{} of {} suggestions were right",
+        right,
+        total
+    );
+}
+
+#[test]
+fn test_word_lists() {
+    for words in [&*ADJECTIVES, &*NOUNS] {
+        assert_eq!(words.len(), 256);
+        assert!(words.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(
+            words
+                .iter()
+                .all(|word| !word.is_empty() && word.chars().all(|c| c.is_ascii_lowercase()))
+        );
+    }
+}
+
+#[test]
+fn test_word_codes() {
+    let encoder = check_encoder(DEFAULT_ALPHABET);
+
+    let codes = (0..100_000)
+        .map(|id| encoder.encode_words(id))
+        .collect::<HashSet<_>>();
+    assert_eq!(codes.len(), 100_000);
+
+    for id in [0, 1, 12_345, u32::MAX as u64, 1 << 40, u64::MAX] {
+        let code = encoder.encode_words(id);
+        let parts = code.split('-').collect::<Vec<_>>();
+
+        assert_eq!(parts.len(), 3);
+        assert!(ADJECTIVES.contains(&parts[0]) && NOUNS.contains(&parts[1]));
+        assert_eq!(encoder.decode(&code), Some(id));
+    }
+
+    // 32-bit IDs never need more than a 5-digit number
+    assert!(
+        (0..1_000)
+            .map(|id| encoder.encode_words(u32::MAX as u64 - id))
+            .all(|code| code.rsplit('-').next().unwrap().len() <= 5)
+    );
+
+    let code = encoder.encode_words(7);
+    let (words, number) = code.rsplit_once('-').unwrap();
+    for typo in [
+        format!("{}-0{}", words, number),
+        format!("{}-+{}", words, number),
+        format!("{}-{}-1", words, number),
+        format!("{}-{}", words, "x"),
+        format!("xyz-{}", code.split_once('-').unwrap().1),
+        code.to_uppercase(),
+    ] {
+        assert_eq!(encoder.decode(&typo), None, "{}", typo);
+    }
+
+    assert!(matches!(
+        ShortCodeEncoder::new(ShortCodeConfig::builder().key(42).alphabet("abc-").build()),
+        Err(ShortCodeError::ReservedCharacter('-'))
+    ));
+}
+
+#[test]
+fn test_aliases_never_read_as_word_codes() {
+    let encoder = encoder(42);
+
+    assert!(encoder.can_be_alias("promo"));
+    assert!(encoder.can_be_alias("brave-tiger"));
+    assert!(!encoder.can_be_alias(&encoder.encode_words(7)));
+    // Not generated yet, but it would be one day
+    assert!(!encoder.can_be_alias(&encoder.encode_words(1 << 40)));
+}
+
+#[test]
+fn test_hashed_codes() {
+    let encoder = check_encoder(DEFAULT_ALPHABET);
+
+    let code = encoder.hashed("https://example.com/a", "user", 0).unwrap();
+    assert_eq!(code.chars().count(), 9);
+    assert!(encoder.is_well_formed(&code));
+    assert_eq!(
+        encoder.hashed("https://example.com/a", "user", 0),
+        Some(code.clone())
+    );
+
+    // Equivalent spellings of the URL hash the same
+    assert_eq!(
+        encoder.hashed(" HTTPS://Example.COM:443/b/../a", "user", 0),
+        Some(code.clone())
+    );
+    assert_ne!(
+        encoder.hashed("https://example.com/a", "other", 0),
+        Some(code.clone())
+    );
+    assert_ne!(
+        keyed_encoder(7).hashed("https://example.com/a", "user", 0),
+        keyed_encoder(8).hashed("https://example.com/a", "user", 0)
+    );
+
+    // Each attempt adds a character of the digest
+    let longer = encoder.hashed("https://example.com/a", "user", 1).unwrap();
+    assert_eq!(longer.chars().count(), 10);
+    assert_eq!(longer[..8], code[..8]);
+    assert!(encoder.is_well_formed(&longer));
+
+    let longest = (0..)
+        .map_while(|attempt| encoder.hashed("https://example.com/a", "user", attempt))
+        .last()
+        .unwrap();
+    assert!(longest.chars().count() > 40);
+    assert!(encoder.is_well_formed(&longest));
+    assert_eq!(encoder.decode(&longest), None);
+}
+
+fn keyed_encoder(key: u64) -> ShortCodeEncoder {
+    ShortCodeEncoder::new(ShortCodeConfig::builder().key(key).min_length(8).build()).unwrap()
+}
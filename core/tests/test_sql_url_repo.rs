@@ -0,0 +1,279 @@
+mod utils;
+
+use futures_util::TryStreamExt;
+use map_macro::hash_map;
+use pretty_assertions::assert_eq;
+use sqlx::Row;
+use utils::init_tracing;
+use wee_core::{
+    domain::{
+        entities::url::Url,
+        repos::{
+            page::{PageRequest, encode_cursor},
+            url_query::UrlQuery,
+            url_repo::{
+                DeleteUrlError, GetUrlError, InsertUrlError, ListUrlsError, ReplaceUrlError,
+                UrlRepo, UrlRepoError,
+            },
+        },
+    },
+    outbound::sql::{SqlConfig, url_repo::SqlUrlRepo},
+    test_utils::url,
+};
+
+async fn set_up() -> SqlUrlRepo {
+    init_tracing();
+
+    let config = SqlConfig::builder()
+        .url("sqlite::memory:")
+        .tables(hash_map! {
+            "url_repo".to_string() => "urls".to_string(),
+        })
+        .build();
+
+    let repo = SqlUrlRepo::new(config).await.unwrap();
+    repo.ensure_indexes().await.unwrap();
+    repo
+}
+
+#[tokio::test]
+async fn test_ensure_indexes() {
+    let repo = set_up().await;
+    // Running twice must be a no-op.
+    repo.ensure_indexes().await.unwrap();
+
+    let names = sqlx::query("SELECT name FROM sqlite_master WHERE type = 'index' ORDER BY name")
+        .fetch_all(&repo.pool)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get::<String, _>("name"))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        names,
+        vec![
+            "urls__alias__uniq_where_alias",
+            "urls__short__uniq",
+            "urls__user_id_long__uniq",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_insert_get_and_replace() {
+    let repo = set_up().await;
+    let url = url("a")
+        .long("https://example.com")
+        .alias("example")
+        .expiration_date("2030-01-01T00:00:00Z".parse().unwrap())
+        .call();
+
+    repo.insert(url.clone()).await.unwrap();
+    assert_eq!(repo.get("a").await.unwrap(), url);
+
+    assert!(matches!(
+        repo.insert(url.clone()).await,
+        Err(UrlRepoError::Insert(InsertUrlError::AlreadyExists))
+    ));
+
+    let replacement = Url {
+        long: "https://example.org".to_string(),
+        fallback_url: Some("https://example.org/expired".to_string()),
+        ..url
+    };
+    repo.replace_if_exists(replacement.clone()).await.unwrap();
+    assert_eq!(repo.get("a").await.unwrap(), replacement);
+
+    assert!(matches!(
+        repo.replace_if_exists(Url {
+            short: "b".to_string(),
+            ..replacement
+        })
+        .await,
+        Err(UrlRepoError::Replace(ReplaceUrlError::NotFound(short))) if short == "b"
+    ));
+}
+
+#[tokio::test]
+async fn test_find() {
+    let repo = set_up().await;
+    let url = url("a").long("https://example.com").call();
+
+    repo.insert(url.clone()).await.unwrap();
+
+    let found = repo
+        .find(UrlQuery::ByShortOrAlias("a".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(found, Some(url));
+
+    assert!(matches!(
+        repo.find(UrlQuery::ByUserAndLong {
+            user_id: "someone_else".to_string(),
+            long: "https://example.com".to_string(),
+        })
+        .await,
+        Err(UrlRepoError::Get(GetUrlError::NotFound))
+    ));
+}
+
+#[tokio::test]
+async fn test_lifecycle() {
+    let repo = set_up().await;
+
+    for short in ["c", "a", "b"] {
+        repo.insert(url(short).alias(format!("alias-{}", short)).call())
+            .await
+            .unwrap();
+    }
+    repo.insert(url("d").alias("alias-d").user_id("other_user").call())
+        .await
+        .unwrap();
+
+    assert_eq!(repo.count().await.unwrap(), 4);
+    assert_eq!(repo.count_by_user("test_user").await.unwrap(), 3);
+
+    let first = repo
+        .list_by_user("test_user", PageRequest::builder().limit(2).build())
+        .await
+        .unwrap();
+    assert_eq!(
+        first
+            .items
+            .iter()
+            .map(|url| url.short.as_str())
+            .collect::<Vec<_>>(),
+        vec!["a", "b"]
+    );
+    assert_eq!(first.next_cursor, Some(encode_cursor("b")));
+
+    let second = repo
+        .list_by_user(
+            "test_user",
+            PageRequest::builder()
+                .limit(2)
+                .maybe_after(first.next_cursor)
+                .build(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        second
+            .items
+            .iter()
+            .map(|url| url.short.as_str())
+            .collect::<Vec<_>>(),
+        vec!["c"]
+    );
+    assert_eq!(second.next_cursor, None);
+
+    let mut scanned = repo
+        .scan(2)
+        .await
+        .unwrap()
+        .map_ok(|url| url.short)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    scanned.sort();
+    assert_eq!(scanned, vec!["a", "b", "c", "d"]);
+
+    assert_eq!(repo.delete("a").await.unwrap().short, "a");
+    assert!(matches!(
+        repo.delete("a").await,
+        Err(UrlRepoError::Delete(DeleteUrlError::NotFound(short))) if short == "a"
+    ));
+    assert_eq!(repo.count().await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn test_list_invalid_page() {
+    let repo = set_up().await;
+
+    for page in [
+        PageRequest::builder().limit(0).build(),
+        PageRequest::builder().after("").build(),
+        PageRequest::builder().after("not a cursor").build(),
+    ] {
+        assert!(matches!(
+            repo.list_by_user("test_user", page).await,
+            Err(UrlRepoError::List(
+                ListUrlsError::InvalidLimit(_) | ListUrlsError::InvalidCursor(_)
+            ))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_delete_expired() {
+    let repo = set_up().await;
+    let now = "2030-01-01T12:00:00Z"
+        .parse::<chrono::DateTime<chrono::Utc>>()
+        .unwrap();
+
+    for (short, expiration_date) in [
+        ("a", Some(now - chrono::Duration::seconds(1))),
+        ("b", Some(now)),
+        ("c", Some(now + chrono::Duration::seconds(1))),
+        ("d", None),
+    ] {
+        repo.insert(
+            url(short)
+                .alias(format!("alias-{}", short))
+                .maybe_expiration_date(expiration_date)
+                .call(),
+        )
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(repo.delete_expired(now, 1).await.unwrap().len(), 1);
+    assert_eq!(repo.delete_expired(now, 10).await.unwrap().len(), 1);
+    assert!(repo.delete_expired(now, 10).await.unwrap().is_empty());
+    assert_eq!(repo.count().await.unwrap(), 2);
+}
+
+#[tokio::test]
+async fn test_add_fallback_url_to_existing_table() {
+    init_tracing();
+    let path = std::env::temp_dir().join(format!("wee-fallback-url-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let db_url = format!("sqlite://{}?mode=rwc", path.display());
+
+    // A table created before fallback URLs existed
+    sqlx::any::install_default_drivers();
+    let pool = sqlx::AnyPool::connect(&db_url).await.unwrap();
+    sqlx::query(
+        "CREATE TABLE urls (
+            long            TEXT NOT NULL,
+            short           TEXT NOT NULL,
+            alias           TEXT,
+            expiration_date TEXT,
+            created_at      TEXT NOT NULL,
+            updated_at      TEXT NOT NULL,
+            user_id         TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    pool.close().await;
+
+    let config = SqlConfig::builder()
+        .url(db_url)
+        .tables(hash_map! {
+            "url_repo".to_string() => "urls".to_string(),
+        })
+        .build();
+    let repo = SqlUrlRepo::new(config.clone()).await.unwrap();
+    let url = url("a").fallback_url("https://example.com/expired").call();
+    repo.insert(url.clone()).await.unwrap();
+    assert_eq!(repo.get("a").await.unwrap(), url);
+
+    // The column is only added once
+    repo.pool.close().await;
+    SqlUrlRepo::new(config).await.unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use pretty_assertions::assert_eq;
+use wee_core::{domain::entities::url::Url, test_utils::url};
+
+#[test]
+fn test_parse_expiration_date() {
+    assert_eq!(
+        Url::parse_expiration_date("2030-01-01T09:30:00+02:00").unwrap(),
+        "2030-01-01T07:30:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+    // A legacy date lasts until the end of that day
+    assert_eq!(
+        Url::parse_expiration_date("2030-01-01").unwrap(),
+        "2030-01-02T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+    assert!(Url::parse_expiration_date("tomorrow").is_err());
+}
+
+#[test]
+fn test_expiration_date_round_trip() {
+    let expiration_date = "2030-01-01T07:30:00Z".parse::<DateTime<Utc>>().unwrap();
+    let url = url("a").expiration_date(expiration_date).call();
+
+    let json = serde_json::to_value(&url).unwrap();
+    assert_eq!(json["expirationDate"], "2030-01-01T07:30:00Z");
+    assert_eq!(serde_json::from_value::<Url>(json).unwrap(), url);
+
+    let legacy = serde_json::json!({
+        "long": "https://example.com",
+        "short": "a",
+        "alias": null,
+        "expirationDate": "2030-01-01",
+        "createdAt": "2025-01-01T00:00:00",
+        "updatedAt": "2025-01-01T00:00:00",
+        "userId": "test_user",
+    });
+    assert_eq!(
+        serde_json::from_value::<Url>(legacy)
+            .unwrap()
+            .expiration_date,
+        Some("2030-01-02T00:00:00Z".parse::<DateTime<Utc>>().unwrap())
+    );
+}
+
+#[test]
+fn test_expired() {
+    assert!(
+        url("a")
+            .expiration_date(Utc::now() - chrono::Duration::seconds(1))
+            .call()
+            .expired()
+    );
+    assert!(
+        !url("a")
+            .expiration_date(Utc::now() + chrono::Duration::hours(1))
+            .call()
+            .expired()
+    );
+    assert!(!url("a").call().expired());
+}
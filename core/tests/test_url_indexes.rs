@@ -0,0 +1,43 @@
+use pretty_assertions::assert_eq;
+use wee_core::domain::{
+    entities::{Entity, url::Url},
+    metadata::url_indexes::{UrlIndex, UrlIndexes, UrlIndexesError},
+};
+
+#[test]
+fn test_url_field_names() {
+    assert_eq!(
+        Url::field_names(),
+        &[
+            "long",
+            "short",
+            "alias",
+            "expirationDate",
+            "fallbackUrl",
+            "createdAt",
+            "updatedAt",
+            "userId",
+        ]
+    );
+}
+
+#[test]
+fn test_default_indexes_are_valid() {
+    UrlIndexes::default().validate().unwrap();
+}
+
+#[test]
+fn test_unknown_index_key() {
+    let indexes = UrlIndexes::new(vec![
+        UrlIndex::builder()
+            .keys(vec!["user_id".to_string(), "long".to_string()])
+            .is_unique(true)
+            .is_sparse(false)
+            .build(),
+    ]);
+
+    assert!(matches!(
+        indexes.validate(),
+        Err(UrlIndexesError::UnknownKey { key, .. }) if key == "user_id"
+    ));
+}
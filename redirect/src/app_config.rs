@@ -1,4 +1,9 @@
-use wee_core::outbound::{mongodb::MongoConfig, redis::RedisConfig};
+use wee_core::{
+    domain::short_code::ShortCodeConfig,
+    outbound::{mongodb::MongoConfig, redis::RedisConfig},
+};
+
+use crate::outbound::in_memory::redirect_service_cache::InMemoryCacheConfig;
 
 nest! {
     #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Builder)]*
@@ -11,6 +16,8 @@ nest! {
         },
         pub mongodb: MongoConfig,
         pub redis: RedisConfig,
+        pub in_memory_cache: InMemoryCacheConfig,
+        pub short_code: ShortCodeConfig,
     }
 }
 
@@ -40,6 +47,7 @@ impl AppConfig {
 mod tests {
     use map_macro::hash_map;
     use pretty_assertions::assert_eq;
+    use std::num::NonZeroU64;
 
     use super::*;
 
@@ -65,8 +73,25 @@ mod tests {
                     .host("localhost")
                     .port(6379)
                     .dbs(hash_map! {
-                        "redirect".to_string() => 0,
+                        "url_cache".to_string() => 0,
                     })
+                    .namespace("wee")
+                    .max_ttl_secs(NonZeroU64::new(86_400).unwrap())
+                    .build(),
+            )
+            .in_memory_cache(
+                InMemoryCacheConfig::builder()
+                    .enabled(true)
+                    .max_capacity(10_000)
+                    .ttl_secs(30)
+                    .build(),
+            )
+            .short_code(
+                ShortCodeConfig::builder()
+                    .key(8_141_361_219_538_327_423)
+                    .alphabet("0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz")
+                    .min_length(1)
+                    .check_character(false)
                     .build(),
             )
             .build();
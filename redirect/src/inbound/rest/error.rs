@@ -13,9 +13,12 @@ impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
         match self {
             Self::RedirectServiceError(err) => match err {
-                RedirectServiceError::UrlNotFound(_) => {
+                RedirectServiceError::UrlNotFound(_) | RedirectServiceError::InvalidCode { .. } => {
                     (StatusCode::NOT_FOUND, err.to_string()).into_response()
                 }
+                RedirectServiceError::UrlExpired(_) => {
+                    (StatusCode::GONE, err.to_string()).into_response()
+                }
                 RedirectServiceError::UrlRepoError(err) => match err {
                     UrlRepoError::Get(GetUrlError::NotFound) => {
                         (StatusCode::NOT_FOUND, err.to_string()).into_response()
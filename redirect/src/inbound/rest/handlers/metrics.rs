@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+
+use crate::outbound::in_memory::redirect_service_cache::RedirectCacheStats;
+
+/// Exports hits, misses and hit ratio of the in-memory and the Redis cache in the
+/// Prometheus text format.
+pub async fn metrics(State(stats): State<Arc<RedirectCacheStats>>) -> String {
+    let snapshot = stats.snapshot();
+    let tiers = [("local", snapshot.local), ("remote", snapshot.remote)];
+
+    [
+        (
+            "wee_redirect_cache_hits_total",
+            "Lookups answered by the cache tier",
+            "counter",
+            tiers.map(|(tier, stats)| (tier, stats.hits.to_string())),
+        ),
+        (
+            "wee_redirect_cache_misses_total",
+            "Lookups the cache tier had to pass on",
+            "counter",
+            tiers.map(|(tier, stats)| (tier, stats.misses.to_string())),
+        ),
+        (
+            "wee_redirect_cache_hit_ratio",
+            "Share of lookups answered by the cache tier, NaN before the first one",
+            "gauge",
+            tiers.map(|(tier, stats)| {
+                (
+                    tier,
+                    stats
+                        .hit_ratio
+                        .map_or("NaN".to_string(), |ratio| ratio.to_string()),
+                )
+            }),
+        ),
+    ]
+    .iter()
+    .map(|(name, help, kind, values)| {
+        let samples: String = values
+            .iter()
+            .map(|(tier, value)| format!("{name}{{tier=\"{tier}\"}} {value}\n"))
+            .collect();
+        format!("# HELP {name} {help}\n# TYPE {name} {kind}\n{samples}")
+    })
+    .collect()
+}
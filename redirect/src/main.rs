@@ -6,10 +6,14 @@ use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use wee_core::outbound::mongodb::url_repo::MongoUrlRepo;
+use wee_core::{domain::short_code::ShortCodeEncoder, outbound::mongodb::url_repo::MongoUrlRepo};
 use wee_redirect::{
-    app_config::AppConfig, inbound::rest::handlers::redirect::redirect,
-    outbound::redis::redirect_service_cache::RedisRedirectServiceCache,
+    app_config::AppConfig,
+    inbound::rest::handlers::{metrics::metrics, redirect::redirect},
+    outbound::{
+        in_memory::redirect_service_cache::InMemoryRedirectServiceCache,
+        redis::redirect_service_cache::RedisRedirectServiceCache,
+    },
     services::redirect_service::RedirectService,
 };
 
@@ -27,21 +31,52 @@ async fn main() {
     let config = AppConfig::load();
     info!("Config: {:#?}", config);
     let mongo_url_repo = MongoUrlRepo::new(config.mongodb.clone()).await.unwrap();
-    mongo_url_repo.ensure_indexes().await.unwrap();
+    match std::env::args().nth(1).as_deref() {
+        Some("plan-indexes") => {
+            mongo_url_repo.plan_indexes().await.unwrap();
+            return;
+        }
+        Some("ensure-indexes") => {
+            mongo_url_repo.ensure_indexes().await.unwrap();
+            return;
+        }
+        Some("migrate-expirations") => {
+            mongo_url_repo.migrate_expiration_dates().await.unwrap();
+            return;
+        }
+        _ => mongo_url_repo.ensure_indexes_on_startup().await.unwrap(),
+    }
 
     let redis_redirect_service_cache = RedisRedirectServiceCache::new(config.redis.clone())
         .await
         .unwrap();
 
-    let redirect_service = Arc::new(RedirectService::new(
+    let url_cache = redis_redirect_service_cache.url_cache.clone();
+    let in_memory_redirect_service_cache = InMemoryRedirectServiceCache::new(
+        config.in_memory_cache.clone(),
         redis_redirect_service_cache,
+    );
+    in_memory_redirect_service_cache.invalidate_on(move || {
+        let url_cache = url_cache.clone();
+        async move { url_cache.evictions().await }
+    });
+    let cache_stats = in_memory_redirect_service_cache.stats.clone();
+
+    let redirect_service = Arc::new(RedirectService::new(
+        in_memory_redirect_service_cache,
         mongo_url_repo,
+        ShortCodeEncoder::new(config.short_code.clone()).unwrap(),
     ));
 
     let router = Router::new()
         .route("/ping", get(|| async { "Pong!" }))
         .route("/{code}", get(redirect))
         .with_state(redirect_service)
+        .merge(
+            Router::new()
+                .route("/metrics", get(metrics))
+                .with_state(cache_stats),
+        )
         .layer(ServiceBuilder::new().layer(TraceLayer::new_for_http()));
 
     let listener = TcpListener::bind(format!("{}:{}", config.app.host, config.app.port))
@@ -0,0 +1 @@
+pub mod redirect_service_cache;
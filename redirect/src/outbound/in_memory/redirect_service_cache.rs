@@ -0,0 +1,189 @@
+use std::{
+    fmt::Display,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use futures_util::{Stream, StreamExt};
+use moka::sync::Cache;
+use tokio::task::JoinHandle;
+use wee_core::domain::entities::url::Url;
+
+use crate::services::redirect_service::{cache::RedirectServiceCache, error::RedirectServiceError};
+
+nest! {
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]*
+    pub struct InMemoryCacheConfig {
+        /// Without it every lookup goes straight to the wrapped cache
+        pub enabled: bool,
+        /// How many codes to keep, the least valuable ones are evicted first
+        pub max_capacity: u64,
+        /// How long a code is kept, also the longest another instance's eviction can go
+        /// unnoticed if the eviction channel is down
+        pub ttl_secs: u64,
+        /// How long to wait before subscribing to evictions again after losing them
+        #[builder(default = 1)]
+        #[serde(default = "default_resubscribe_secs")]
+        pub resubscribe_secs: u64,
+    }
+}
+
+fn default_resubscribe_secs() -> u64 {
+    1
+}
+
+/// Hits and misses of one cache tier.
+#[derive(Debug, Default)]
+pub struct CacheTierStats {
+    pub hits: AtomicU64,
+    pub misses: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CacheTierSnapshot {
+    pub hits: u64,
+    pub misses: u64,
+    /// `None` until the tier was asked at all
+    pub hit_ratio: Option<f64>,
+}
+
+impl CacheTierStats {
+    pub fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> CacheTierSnapshot {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+
+        CacheTierSnapshot {
+            hits,
+            misses,
+            hit_ratio: (hits + misses > 0).then(|| hits as f64 / (hits + misses) as f64),
+        }
+    }
+}
+
+/// How often each tier answered. The wrapped cache is only asked on a local miss.
+#[derive(Debug, Default)]
+pub struct RedirectCacheStats {
+    pub local: CacheTierStats,
+    pub remote: CacheTierStats,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RedirectCacheSnapshot {
+    pub local: CacheTierSnapshot,
+    pub remote: CacheTierSnapshot,
+}
+
+impl RedirectCacheStats {
+    pub fn snapshot(&self) -> RedirectCacheSnapshot {
+        RedirectCacheSnapshot {
+            local: self.local.snapshot(),
+            remote: self.remote.snapshot(),
+        }
+    }
+}
+
+/// Keeps the most requested codes in process, in front of a shared cache such as
+/// `RedisRedirectServiceCache`, so that popular links skip the round trip.
+pub struct InMemoryRedirectServiceCache<C: RedirectServiceCache> {
+    pub config: InMemoryCacheConfig,
+    pub inner: C,
+    /// By code, so a URL may be held under its short code and its alias
+    pub urls: Cache<String, Url>,
+    pub stats: Arc<RedirectCacheStats>,
+}
+
+impl<C: RedirectServiceCache> RedirectServiceCache for InMemoryRedirectServiceCache<C> {
+    async fn get(&self, code: &str) -> Result<Option<Url>, RedirectServiceError> {
+        if self.config.enabled {
+            let url = self.urls.get(code);
+            self.stats.local.record(url.is_some());
+            if url.is_some() {
+                return Ok(url);
+            }
+        }
+
+        let url = self.inner.get(code).await?;
+        self.stats.remote.record(url.is_some());
+        if let Some(url) = url.as_ref().filter(|_| self.config.enabled) {
+            self.urls.insert(code.to_string(), url.clone());
+        }
+
+        Ok(url)
+    }
+
+    async fn set(&self, url: Url) -> Result<(), RedirectServiceError> {
+        if self.config.enabled {
+            for code in std::iter::once(&url.short).chain(url.alias.as_ref()) {
+                self.urls.insert(code.clone(), url.clone());
+            }
+        }
+
+        self.inner.set(url).await
+    }
+}
+
+impl<C: RedirectServiceCache> InMemoryRedirectServiceCache<C> {
+    pub fn new(config: InMemoryCacheConfig, inner: C) -> Self {
+        let urls = Cache::builder()
+            .max_capacity(config.max_capacity)
+            .time_to_live(Duration::from_secs(config.ttl_secs))
+            .build();
+
+        Self {
+            config,
+            inner,
+            urls,
+            stats: Arc::default(),
+        }
+    }
+
+    pub fn invalidate(&self, codes: &[String]) {
+        for code in codes {
+            self.urls.invalidate(code);
+        }
+    }
+
+    /// Drops the codes in every batch `subscribe` yields, e.g. the wrapped cache's
+    /// evictions on other instances. Whenever subscribing fails or the stream ends,
+    /// every code is dropped, as evictions may have been missed, and it subscribes
+    /// again after `resubscribe_secs`.
+    pub fn invalidate_on<F, Fut, S, E>(&self, subscribe: F) -> JoinHandle<()>
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<S, E>> + Send + 'static,
+        S: Stream<Item = Vec<String>> + Send + 'static,
+        E: Display + Send + 'static,
+    {
+        let urls = self.urls.clone();
+        let resubscribe = Duration::from_secs(self.config.resubscribe_secs);
+
+        tokio::spawn(async move {
+            loop {
+                match subscribe().await {
+                    Ok(evictions) => {
+                        let mut evictions = std::pin::pin!(evictions);
+                        while let Some(codes) = evictions.next().await {
+                            debug!("Invalidating {:?}", codes);
+                            for code in codes {
+                                urls.invalidate(&code);
+                            }
+                        }
+                        warn!("Lost the evictions, dropping every cached code");
+                    }
+                    Err(err) => warn!("Failed to subscribe to evictions: {}", err),
+                }
+
+                urls.invalidate_all();
+                tokio::time::sleep(resubscribe).await;
+            }
+        })
+    }
+}
@@ -10,6 +10,23 @@ pub enum RedirectServiceError {
     #[error("Url Not Found: {0}")]
     UrlNotFound(String),
 
+    /// A code that neither passes the check character nor matches an alias
+    #[error("Invalid Code: {code}{}", did_you_mean(.suggestion))]
+    InvalidCode {
+        code: String,
+        suggestion: Option<String>,
+    },
+
+    #[error("Url Expired: {0}")]
+    UrlExpired(String),
+
     #[error("Cache Error: {0}")]
     CacheError(#[from] RedisRedirectServiceCacheError),
 }
+
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    suggestion
+        .as_ref()
+        .map(|suggestion| format!(", did you mean {}?", suggestion))
+        .unwrap_or_default()
+}
@@ -3,10 +3,19 @@ pub mod error;
 
 use cache::RedirectServiceCache;
 use error::RedirectServiceError;
-use mongodb::bson::doc;
-use wee_core::domain::repos::url_repo::UrlRepo;
+use wee_core::domain::{
+    entities::url::Url,
+    repos::{
+        url_query::UrlQuery,
+        url_repo::{GetUrlError, UrlRepo, UrlRepoError},
+    },
+    short_code::ShortCodeEncoder,
+};
 
 pub trait RedirectServiceTrait: Send + Sync {
+    /// Resolves `code` to the destination to redirect to.
+    ///
+    /// An expired link resolves to its fallback URL, or fails with `UrlExpired`.
     fn redirect(
         &self,
         code: &str,
@@ -16,33 +25,79 @@ pub trait RedirectServiceTrait: Send + Sync {
 pub struct RedirectService<C: RedirectServiceCache, R: UrlRepo> {
     pub cache: C,
     pub repository: R,
+    pub short_code: ShortCodeEncoder,
 }
 
 impl<C: RedirectServiceCache, R: UrlRepo> RedirectServiceTrait for RedirectService<C, R> {
     async fn redirect(&self, code: &str) -> Result<String, RedirectServiceError> {
-        if let Some(url) = self.cache.get(code).await? {
-            return Ok(url.long);
+        let Some(query) = self.query(code) else {
+            return Err(self.not_found(code));
+        };
+
+        if let Some(url) = self.cache.get(code).await?.filter(|url| query.matches(url)) {
+            return destination(code, url);
         }
 
-        let url = self
-            .repository
-            .find(doc! {
-                "$or": [
-                    { "alias": code },
-                    { "short": code },
-                ]
-            })
-            .await?
-            .ok_or(RedirectServiceError::UrlNotFound(code.to_string()))?;
+        let url = match self.repository.find(query.clone()).await {
+            Ok(Some(url)) => url,
+            Ok(None) | Err(UrlRepoError::Get(GetUrlError::NotFound)) => {
+                return Err(self.not_found(code));
+            }
+            Err(err) => return Err(err.into()),
+        };
 
-        self.cache.set(url.clone()).await?;
+        if !url.expired() {
+            self.cache.set(url.clone()).await?;
+        }
 
-        Ok(url.long)
+        destination(code, url)
+    }
+}
+
+fn destination(code: &str, url: Url) -> Result<String, RedirectServiceError> {
+    match (url.expired(), url.fallback_url) {
+        (false, _) => Ok(url.long),
+        (true, Some(fallback_url)) => {
+            debug!("Url {} expired, redirecting to fallback", code);
+            Ok(fallback_url)
+        }
+        (true, None) => Err(RedirectServiceError::UrlExpired(code.to_string())),
     }
 }
 
 impl<C: RedirectServiceCache, R: UrlRepo> RedirectService<C, R> {
-    pub fn new(cache: C, repository: R) -> Self {
-        Self { cache, repository }
+    pub fn new(cache: C, repository: R, short_code: ShortCodeEncoder) -> Self {
+        Self {
+            cache,
+            repository,
+            short_code,
+        }
+    }
+
+    /// Every code is looked up as a short code too, even one the current alphabet and
+    /// minimum length can't produce: it may predate a change to them. Only in `strict`
+    /// mode is such a code looked up as an alias alone, or not at all when it can't be
+    /// an alias either.
+    pub fn query(&self, code: &str) -> Option<UrlQuery> {
+        if !self.short_code.config.strict || self.short_code.is_well_formed(code) {
+            Some(UrlQuery::ByShortOrAlias(code.to_string()))
+        } else if self.short_code.can_be_alias(code) {
+            Some(UrlQuery::ByAlias(code.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// With check characters, an unknown code that fails the check was most likely
+    /// mistyped, so it is reported as invalid along with the code that was probably meant.
+    pub fn not_found(&self, code: &str) -> RedirectServiceError {
+        if self.short_code.config.check_character && !self.short_code.is_well_formed(code) {
+            RedirectServiceError::InvalidCode {
+                code: code.to_string(),
+                suggestion: self.short_code.suggest(code),
+            }
+        } else {
+            RedirectServiceError::UrlNotFound(code.to_string())
+        }
     }
 }
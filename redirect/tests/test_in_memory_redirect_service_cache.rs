@@ -0,0 +1,179 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::extract::State;
+use futures_util::stream;
+use pretty_assertions::assert_eq;
+use tokio::sync::mpsc;
+use wee_core::{domain::entities::url::Url, test_utils::url};
+use wee_redirect::{
+    inbound::rest::handlers::metrics::metrics,
+    outbound::in_memory::redirect_service_cache::{
+        CacheTierSnapshot, InMemoryCacheConfig, InMemoryRedirectServiceCache,
+    },
+    services::redirect_service::{cache::RedirectServiceCache, error::RedirectServiceError},
+};
+
+#[derive(Default)]
+struct CountingCache {
+    urls: Mutex<Vec<Url>>,
+    gets: AtomicUsize,
+}
+
+impl RedirectServiceCache for CountingCache {
+    async fn get(&self, code: &str) -> Result<Option<Url>, RedirectServiceError> {
+        self.gets.fetch_add(1, Ordering::Relaxed);
+        Ok(self
+            .urls
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|url| url.short == code || url.alias.as_deref() == Some(code))
+            .cloned())
+    }
+
+    async fn set(&self, url: Url) -> Result<(), RedirectServiceError> {
+        self.urls.lock().unwrap().push(url);
+        Ok(())
+    }
+}
+
+fn set_up(enabled: bool, urls: Vec<Url>) -> InMemoryRedirectServiceCache<CountingCache> {
+    let config = InMemoryCacheConfig::builder()
+        .enabled(enabled)
+        .max_capacity(100)
+        .ttl_secs(60)
+        .resubscribe_secs(3_600)
+        .build();
+    let inner = CountingCache {
+        urls: Mutex::new(urls),
+        ..Default::default()
+    };
+
+    InMemoryRedirectServiceCache::new(config, inner)
+}
+
+fn tier(hits: u64, misses: u64) -> CacheTierSnapshot {
+    CacheTierSnapshot {
+        hits,
+        misses,
+        hit_ratio: (hits + misses > 0).then(|| hits as f64 / (hits + misses) as f64),
+    }
+}
+
+/// Moka applies invalidations right away, but the subscription runs on its own task.
+async fn wait_until(condition: impl Fn() -> bool) {
+    for _ in 0..100 {
+        if condition() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("Condition not met in time");
+}
+
+#[tokio::test]
+async fn test_local_tier_answers_repeated_codes() {
+    let cache = set_up(true, vec![url("a").alias("alias-a").call()]);
+
+    assert_eq!(cache.get("a").await.unwrap().unwrap().short, "a");
+    assert_eq!(cache.get("a").await.unwrap().unwrap().short, "a");
+    assert_eq!(cache.get("missing").await.unwrap(), None);
+
+    assert_eq!(cache.inner.gets.load(Ordering::Relaxed), 2);
+    let stats = cache.stats.snapshot();
+    assert_eq!(stats.local, tier(1, 2));
+    assert_eq!(stats.remote, tier(1, 1));
+}
+
+#[tokio::test]
+async fn test_metrics() {
+    let cache = set_up(true, vec![url("a").alias("alias-a").call()]);
+    cache.get("a").await.unwrap();
+    cache.get("a").await.unwrap();
+
+    let metrics = metrics(State(cache.stats.clone())).await;
+
+    assert!(metrics.contains("# TYPE wee_redirect_cache_hits_total counter\n"));
+    assert!(metrics.contains("wee_redirect_cache_hits_total{tier=\"local\"} 1\n"));
+    assert!(metrics.contains("wee_redirect_cache_misses_total{tier=\"remote\"} 0\n"));
+    assert!(metrics.contains("wee_redirect_cache_hit_ratio{tier=\"local\"} 0.5\n"));
+}
+
+#[tokio::test]
+async fn test_set_caches_short_code_and_alias() {
+    let cache = set_up(true, vec![]);
+    cache.set(url("a").alias("alias-a").call()).await.unwrap();
+
+    assert_eq!(cache.get("a").await.unwrap().unwrap().short, "a");
+    assert_eq!(cache.get("alias-a").await.unwrap().unwrap().short, "a");
+    assert_eq!(cache.inner.gets.load(Ordering::Relaxed), 0);
+    assert_eq!(cache.inner.urls.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_disabled_passes_through() {
+    let cache = set_up(false, vec![url("a").alias("alias-a").call()]);
+
+    assert_eq!(cache.get("a").await.unwrap().unwrap().short, "a");
+    assert_eq!(cache.get("a").await.unwrap().unwrap().short, "a");
+
+    assert_eq!(cache.inner.gets.load(Ordering::Relaxed), 2);
+    let stats = cache.stats.snapshot();
+    assert_eq!(stats.local, tier(0, 0));
+    assert_eq!(stats.local.hit_ratio, None);
+    assert_eq!(stats.remote, tier(2, 0));
+}
+
+#[tokio::test]
+async fn test_invalidate() {
+    let cache = set_up(true, vec![url("a").alias("alias-a").call()]);
+    cache.get("a").await.unwrap();
+    cache.invalidate(&["a".to_string()]);
+    cache.get("a").await.unwrap();
+
+    assert_eq!(cache.inner.gets.load(Ordering::Relaxed), 2);
+}
+
+#[tokio::test]
+async fn test_invalidate_on_evictions() {
+    let cache = set_up(true, vec![]);
+    cache.set(url("a").alias("alias-a").call()).await.unwrap();
+    cache.set(url("b").alias("alias-b").call()).await.unwrap();
+
+    let (sender, receiver) = mpsc::unbounded_channel::<Vec<String>>();
+    let receiver = Arc::new(Mutex::new(Some(receiver)));
+    let subscriptions = Arc::new(AtomicUsize::new(0));
+    let handle = cache.invalidate_on({
+        let subscriptions = subscriptions.clone();
+        move || {
+            subscriptions.fetch_add(1, Ordering::Relaxed);
+            let receiver = receiver.lock().unwrap().take();
+            async move {
+                let receiver = receiver.ok_or("Already subscribed")?;
+                Ok::<_, &str>(stream::unfold(receiver, |mut receiver| async move {
+                    receiver.recv().await.map(|codes| (codes, receiver))
+                }))
+            }
+        }
+    });
+
+    sender
+        .send(vec!["a".to_string(), "alias-a".to_string()])
+        .unwrap();
+    wait_until(|| !cache.urls.contains_key("a")).await;
+    assert!(!cache.urls.contains_key("alias-a"));
+    assert!(cache.urls.contains_key("b"));
+
+    // Evictions may be missed until it subscribes again, so everything goes
+    drop(sender);
+    wait_until(|| !cache.urls.contains_key("b")).await;
+    assert_eq!(subscriptions.load(Ordering::Relaxed), 1);
+
+    handle.abort();
+}
@@ -0,0 +1,242 @@
+use std::sync::Mutex;
+
+use chrono::{Duration, Utc};
+use pretty_assertions::assert_eq;
+use wee_core::{
+    domain::{
+        entities::url::Url,
+        repos::url_repo::{GetUrlError, UrlRepo, UrlRepoError},
+        short_code::{ShortCodeConfig, ShortCodeEncoder},
+    },
+    outbound::in_memory::url_repo::InMemoryUrlRepo,
+    test_utils::url,
+};
+use wee_redirect::services::redirect_service::{
+    RedirectService, RedirectServiceTrait, cache::RedirectServiceCache, error::RedirectServiceError,
+};
+
+#[derive(Default)]
+struct MemoryCache {
+    urls: Mutex<Vec<Url>>,
+}
+
+impl RedirectServiceCache for MemoryCache {
+    async fn get(&self, code: &str) -> Result<Option<Url>, RedirectServiceError> {
+        Ok(self
+            .urls
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|url| url.short == code || url.alias.as_deref() == Some(code))
+            .cloned())
+    }
+
+    async fn set(&self, url: Url) -> Result<(), RedirectServiceError> {
+        self.urls.lock().unwrap().push(url);
+        Ok(())
+    }
+}
+
+async fn set_up(urls: Vec<Url>) -> RedirectService<MemoryCache, InMemoryUrlRepo> {
+    set_up_with_min_length(urls, 1).await
+}
+
+async fn set_up_with_min_length(
+    urls: Vec<Url>,
+    min_length: usize,
+) -> RedirectService<MemoryCache, InMemoryUrlRepo> {
+    let short_code = ShortCodeConfig::builder()
+        .key(42)
+        .min_length(min_length)
+        .build();
+    let repository = InMemoryUrlRepo::default();
+    for url in urls {
+        repository.insert(url).await.unwrap();
+    }
+
+    RedirectService::new(
+        MemoryCache::default(),
+        repository,
+        ShortCodeEncoder::new(short_code).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_redirect_live_url() {
+    let service = set_up(vec![
+        url("a")
+            .alias("alias-a")
+            .expiration_date(Utc::now() + Duration::hours(1))
+            .call(),
+    ])
+    .await;
+
+    assert_eq!(
+        service.redirect("alias-a").await.unwrap(),
+        "https://example.com/a"
+    );
+    assert_eq!(service.cache.urls.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_redirect_expired_url() {
+    let service = set_up(vec![
+        url("a")
+            .alias("alias-a")
+            .expiration_date(Utc::now() - Duration::seconds(1))
+            .call(),
+        url("b")
+            .alias("alias-b")
+            .expiration_date(Utc::now() - Duration::seconds(1))
+            .fallback_url("https://example.com/expired")
+            .call(),
+    ])
+    .await;
+
+    assert!(matches!(
+        service.redirect("a").await,
+        Err(RedirectServiceError::UrlExpired(code)) if code == "a"
+    ));
+    assert_eq!(
+        service.redirect("b").await.unwrap(),
+        "https://example.com/expired"
+    );
+    assert!(service.cache.urls.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_redirect_expired_cached_url() {
+    let service = set_up(vec![]).await;
+    service
+        .cache
+        .set(
+            url("a")
+                .alias("alias-a")
+                .expiration_date(Utc::now() - Duration::seconds(1))
+                .call(),
+        )
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        service.redirect("a").await,
+        Err(RedirectServiceError::UrlExpired(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_redirect_codes_from_before_a_config_change() {
+    // "a" is too short to be generated once codes have at least 3 characters
+    let service = set_up_with_min_length(
+        vec![
+            url("a")
+                .alias("alias-a")
+                .expiration_date(Utc::now() + Duration::hours(1))
+                .call(),
+        ],
+        3,
+    )
+    .await;
+
+    assert_eq!(
+        service.redirect("a").await.unwrap(),
+        "https://example.com/a"
+    );
+    assert_eq!(
+        service.redirect("alias-a").await.unwrap(),
+        "https://example.com/a"
+    );
+    assert!(is_not_found(service.redirect("b").await));
+}
+
+fn is_not_found(result: Result<String, RedirectServiceError>) -> bool {
+    matches!(
+        result,
+        Err(RedirectServiceError::UrlNotFound(_))
+            | Err(RedirectServiceError::UrlRepoError(UrlRepoError::Get(
+                GetUrlError::NotFound
+            )))
+    )
+}
+
+#[tokio::test]
+async fn test_redirect_mistyped_code() {
+    let short_code = ShortCodeEncoder::new(
+        ShortCodeConfig::builder()
+            .key(42)
+            .check_character(true)
+            .build(),
+    )
+    .unwrap();
+    let code = short_code.encode(1);
+    let mut typo = code.clone();
+    typo.replace_range(0..1, "-");
+
+    let mut service = set_up(vec![
+        url(&code)
+            .expiration_date(Utc::now() + Duration::hours(1))
+            .call(),
+    ])
+    .await;
+    service.short_code = short_code;
+
+    assert_eq!(
+        service.redirect(&code).await.unwrap(),
+        format!("https://example.com/{}", code)
+    );
+    assert!(matches!(
+        service.redirect(&typo).await,
+        Err(RedirectServiceError::InvalidCode { suggestion, .. }) if suggestion == Some(code)
+    ));
+}
+
+#[tokio::test]
+async fn test_redirect_legacy_code_without_check_character() {
+    let mut service = set_up(vec![
+        url("a")
+            .alias("alias-a")
+            .expiration_date(Utc::now() + Duration::hours(1))
+            .call(),
+    ])
+    .await;
+    service.short_code = ShortCodeEncoder::new(
+        ShortCodeConfig::builder()
+            .key(42)
+            .check_character(true)
+            .build(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        service.redirect("a").await.unwrap(),
+        "https://example.com/a"
+    );
+}
+
+#[tokio::test]
+async fn test_redirect_strict() {
+    let mut service = set_up(vec![
+        url("a")
+            .alias("alias-a")
+            .expiration_date(Utc::now() + Duration::hours(1))
+            .call(),
+    ])
+    .await;
+    service.short_code = ShortCodeEncoder::new(
+        ShortCodeConfig::builder()
+            .key(42)
+            .min_length(3)
+            .strict(true)
+            .build(),
+    )
+    .unwrap();
+
+    // Stored, but neither a short code it can produce nor an alias, so never looked up
+    assert!(is_not_found(service.redirect("a").await));
+    assert!(service.cache.urls.lock().unwrap().is_empty());
+
+    assert_eq!(
+        service.redirect("alias-a").await.unwrap(),
+        "https://example.com/a"
+    );
+}
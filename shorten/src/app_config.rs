@@ -1,6 +1,12 @@
-use wee_core::outbound::{mongodb::MongoConfig, redis::RedisConfig};
+use wee_core::{
+    domain::short_code::ShortCodeConfig,
+    outbound::{mongodb::MongoConfig, redis::RedisConfig},
+};
 
-use crate::outbound::zookeeper::ZooKeeperConfig;
+use crate::{
+    outbound::{id_generator::IdGeneratorConfig, zookeeper::ZooKeeperConfig},
+    services::expiration_sweeper::ExpirationSweeperConfig,
+};
 
 nest! {
     #[derive(Debug, thiserror::Error)]*
@@ -21,7 +27,10 @@ nest! {
         },
         pub mongodb: MongoConfig,
         pub zookeeper: ZooKeeperConfig,
+        pub id_generator: IdGeneratorConfig,
+        pub short_code: ShortCodeConfig,
         pub redis: RedisConfig,
+        pub expiration_sweeper: ExpirationSweeperConfig,
     }
 }
 
@@ -51,10 +60,21 @@ impl AppConfig {
 mod tests {
     use map_macro::hash_map;
     use pretty_assertions::assert_eq;
-    use std::env;
+    use std::{
+        env,
+        num::{NonZeroU32, NonZeroU64},
+    };
     use wee_core::outbound::{mongodb::MongoConfig, redis::RedisConfig};
 
-    use crate::outbound::zookeeper::id_generator::{ShardInfo, ZooKeeperIdGeneratorConfig};
+    use crate::{
+        outbound::{
+            id_generator::IdGeneratorBackend, mongodb::id_generator::MongoIdGeneratorConfig,
+            redis::id_generator::RedisIdGeneratorConfig,
+            snowflake::id_generator::SnowflakeIdGeneratorConfig,
+            zookeeper::id_generator::ZooKeeperIdGeneratorConfig,
+        },
+        services::expiration_sweeper::ExpirationSweeperConfig,
+    };
 
     use super::*;
 
@@ -81,25 +101,63 @@ mod tests {
                     .port(2181)
                     .id_generator(
                         ZooKeeperIdGeneratorConfig::builder()
-                            .shard_info(
-                                ShardInfo::builder()
-                                    .base_path("wee")
-                                    .id(0)
-                                    .start(0)
-                                    .end(1_000)
-                                    .build(),
-                            )
+                            .base_path("wee")
+                            .shard_size(1_000_000)
+                            .shard_count(1_000)
+                            .block_size(1_000)
                             .build(),
                     )
                     .build(),
             )
+            .id_generator(
+                IdGeneratorConfig::builder()
+                    .backend(IdGeneratorBackend::ZooKeeper)
+                    .redis(
+                        RedisIdGeneratorConfig::builder()
+                            .key("wee:id_counter")
+                            .block_size(1_000)
+                            .build(),
+                    )
+                    .mongodb(
+                        MongoIdGeneratorConfig::builder()
+                            .collection("counters")
+                            .counter("urls")
+                            .block_size(1_000)
+                            .build(),
+                    )
+                    .snowflake(
+                        SnowflakeIdGeneratorConfig::builder()
+                            .worker_id(0)
+                            .epoch_ms(1_735_689_600_000)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .short_code(
+                ShortCodeConfig::builder()
+                    .key(8_141_361_219_538_327_423)
+                    .alphabet("0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz")
+                    .min_length(1)
+                    .check_character(false)
+                    .build(),
+            )
             .redis(
                 RedisConfig::builder()
                     .host("localhost")
                     .port(6379)
                     .dbs(hash_map! {
                         "shorten".to_string() => 0,
+                        "url_cache".to_string() => 0,
                     })
+                    .namespace("wee")
+                    .max_ttl_secs(NonZeroU64::new(86_400).unwrap())
+                    .build(),
+            )
+            .expiration_sweeper(
+                ExpirationSweeperConfig::builder()
+                    .enabled(true)
+                    .interval_secs(3_600)
+                    .batch_size(NonZeroU32::new(1_000).unwrap())
                     .build(),
             )
             .build();
@@ -111,9 +169,6 @@ mod tests {
 
         env::set_var("RUN_MODE", "test");
         let config = AppConfig::load();
-        assert_eq!(
-            config.zookeeper.id_generator.shard_info.base_path,
-            "wee-test"
-        );
+        assert_eq!(config.zookeeper.id_generator.base_path, "wee-test");
     }
 }
@@ -1,7 +1,9 @@
 use axum::{http::StatusCode, response::IntoResponse};
 use validator::ValidationErrors;
 
-use crate::services::shorten_service::error::ShortenServiceError;
+use crate::services::shorten_service::{
+    error::ShortenServiceError, id_generator::IdGeneratorError,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ApiError {
@@ -36,12 +38,15 @@ impl IntoResponse for ApiError {
                 (StatusCode::BAD_REQUEST, message).into_response()
             }
             ApiError::ShortenServiceError(error) => match error {
-                ShortenServiceError::AliasTaken(_) => {
+                ShortenServiceError::InvalidAlias(_) | ShortenServiceError::AliasTaken(_) => {
                     (StatusCode::BAD_REQUEST, error.to_string()).into_response()
                 }
                 ShortenServiceError::UrlAlreadyExistedWithAlias(_) => {
                     (StatusCode::BAD_REQUEST, error.to_string()).into_response()
                 }
+                ShortenServiceError::IdGeneratorError(IdGeneratorError::Exhausted) => {
+                    (StatusCode::SERVICE_UNAVAILABLE, error.to_string()).into_response()
+                }
                 _ => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response(),
             },
         }
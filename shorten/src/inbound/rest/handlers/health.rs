@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
+
+use crate::services::shorten_service::ShortenServiceTrait;
+
+/// Fails while the service can't generate IDs, so it stops receiving traffic.
+pub async fn ready<S>(State(shorten_service): State<Arc<S>>) -> (StatusCode, &'static str)
+where
+    S: ShortenServiceTrait,
+{
+    if shorten_service.id_capacity().await.exhausted {
+        (StatusCode::SERVICE_UNAVAILABLE, "Out of IDs")
+    } else if shorten_service.is_degraded() {
+        (StatusCode::SERVICE_UNAVAILABLE, "Degraded")
+    } else {
+        (StatusCode::OK, "Ready")
+    }
+}
+
+/// Exports ID capacity gauges in the Prometheus text format.
+pub async fn metrics<S>(State(shorten_service): State<Arc<S>>) -> String
+where
+    S: ShortenServiceTrait,
+{
+    let capacity = shorten_service.id_capacity().await;
+
+    [
+        (
+            "wee_shorten_ids_leased",
+            "IDs reserved by this replica and not handed out yet",
+            capacity.leased,
+        ),
+        (
+            "wee_shorten_ids_remaining",
+            "IDs left in this replica's shard and in the shards nobody has started yet",
+            capacity.remaining,
+        ),
+        (
+            "wee_shorten_ids_exhausted",
+            "1 while this replica has run out of IDs",
+            capacity.exhausted as u64,
+        ),
+        (
+            "wee_shorten_id_generator_degraded",
+            "1 while the ID generator can't reach its backing store",
+            shorten_service.is_degraded() as u64,
+        ),
+    ]
+    .iter()
+    .map(|(name, help, value)| {
+        format!("# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n",)
+    })
+    .collect()
+}
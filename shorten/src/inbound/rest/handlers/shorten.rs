@@ -1,8 +1,10 @@
 use std::sync::Arc;
 
 use axum::{extract::State, Json};
-use chrono::{NaiveDate, Utc};
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+use serde::{de, Deserialize, Deserializer};
 use validator::{Validate, ValidationError};
+use wee_core::domain::{entities::url::Url, short_code::CodeStyle};
 
 use crate::{
     inbound::rest::error::ApiError,
@@ -16,12 +18,60 @@ pub struct ShortenRequestPayload {
     pub url: String,
     pub user_id: String,
     pub alias: Option<String>,
+    /// An RFC 3339 timestamp, a duration from now such as `7d`, or a `YYYY-MM-DD` date
+    #[serde(default, deserialize_with = "deserialize_expiration_date")]
     #[validate(custom(function = "validate_expiration_date"))]
-    pub expiration_date: Option<NaiveDate>,
+    pub expiration_date: Option<DateTime<Utc>>,
+    /// Where the link redirects once it has expired
+    #[validate(url)]
+    pub fallback_url: Option<String>,
+    /// `compact` (the default), `words` for codes like `brave-tiger-42`, or `hashed` for
+    /// codes derived from the long URL and user
+    #[serde(default)]
+    pub code_style: CodeStyle,
+}
+
+/// Parses an expiration relative to `now`, truncated to whole seconds.
+///
+/// Durations are a positive number followed by `s`, `m`, `h`, `d` or `w`.
+/// Anything else is parsed as an absolute expiration by `Url::parse_expiration_date`.
+pub fn parse_expiration_date(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    let relative = value
+        .char_indices()
+        .last()
+        .and_then(|(position, unit)| Some((value[..position].parse::<u32>().ok()?, unit)));
+
+    let expiration_date = match relative {
+        Some((amount, unit)) => {
+            let amount = i64::from(amount);
+            let duration = match unit {
+                's' => Duration::seconds(amount),
+                'm' => Duration::minutes(amount),
+                'h' => Duration::hours(amount),
+                'd' => Duration::days(amount),
+                'w' => Duration::weeks(amount),
+                _ => return Err(format!("unknown duration unit '{}' in {}", unit, value)),
+            };
+            now.checked_add_signed(duration)
+                .ok_or_else(|| format!("{} is too far in the future", value))?
+        }
+        None => Url::parse_expiration_date(value).map_err(|err| format!("{}: {}", value, err))?,
+    };
+
+    Ok(expiration_date.trunc_subsecs(0))
+}
+
+fn deserialize_expiration_date<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_expiration_date(&value, Utc::now()).map_err(de::Error::custom))
+        .transpose()
 }
 
-fn validate_expiration_date(date: &NaiveDate) -> Result<(), ValidationError> {
-    let must_be_in_future = date > &Utc::now().date_naive();
+fn validate_expiration_date(date: &DateTime<Utc>) -> Result<(), ValidationError> {
+    let must_be_in_future = date > &Utc::now();
 
     if must_be_in_future {
         Ok(())
@@ -37,6 +87,8 @@ impl From<ShortenRequestPayload> for ShortenParams {
             user_id: payload.user_id,
             alias: payload.alias,
             expiration_date: payload.expiration_date,
+            fallback_url: payload.fallback_url,
+            code_style: payload.code_style,
         }
     }
 }
@@ -10,15 +10,17 @@ use tower_http::trace::TraceLayer;
 use tracing::info;
 use tracing_subscriber::{self, layer::SubscriberExt, util::SubscriberInitExt};
 
-use wee_core::outbound::mongodb::url_repo::MongoUrlRepo;
+use wee_core::{domain::short_code::ShortCodeEncoder, outbound::mongodb::url_repo::MongoUrlRepo};
 use wee_shorten::{
     app_config::AppConfig,
-    inbound::rest::handlers::shorten::shorten,
+    inbound::rest::handlers::{
+        health::{metrics, ready},
+        shorten::shorten,
+    },
     outbound::{
-        redis::shorten_service_cache::RedisShortenServiceCache,
-        zookeeper::id_generator::ZooKeeperIdGenerator,
+        id_generator::AnyIdGenerator, redis::shorten_service_cache::RedisShortenServiceCache,
     },
-    services::shorten_service::ShortenService,
+    services::{expiration_sweeper::ExpirationSweeper, shorten_service::ShortenService},
 };
 
 #[tokio::main]
@@ -35,26 +37,50 @@ async fn main() {
     let config = AppConfig::load();
     info!("Config: {:#?}", config);
 
-    let zk_id_generator = ZooKeeperIdGenerator::new(config.zookeeper.clone())
-        .await
-        .unwrap();
-
     let mongo_url_repo = MongoUrlRepo::new(config.mongodb.clone()).await.unwrap();
-    mongo_url_repo.ensure_indexes().await.unwrap();
+    match std::env::args().nth(1).as_deref() {
+        Some("plan-indexes") => {
+            mongo_url_repo.plan_indexes().await.unwrap();
+            return;
+        }
+        Some("ensure-indexes") => {
+            mongo_url_repo.ensure_indexes().await.unwrap();
+            return;
+        }
+        Some("migrate-expirations") => {
+            mongo_url_repo.migrate_expiration_dates().await.unwrap();
+            return;
+        }
+        _ => mongo_url_repo.ensure_indexes_on_startup().await.unwrap(),
+    }
+
+    let id_generator = AnyIdGenerator::new(&config).await.unwrap();
 
     let redis_shorten_service_cache = RedisShortenServiceCache::new(config.redis.clone())
         .await
         .unwrap();
     let shorten_service = Arc::new(ShortenService::new(
-        zk_id_generator,
+        id_generator,
         mongo_url_repo,
         redis_shorten_service_cache,
+        ShortCodeEncoder::new(config.short_code.clone()).unwrap(),
     ));
 
+    if config.expiration_sweeper.enabled {
+        let sweeper = ExpirationSweeper::new(
+            config.expiration_sweeper.clone(),
+            shorten_service.repository.clone(),
+            shorten_service.cache.clone(),
+        );
+        tokio::spawn(sweeper.run());
+    }
+
     let router = Router::new()
         .route("/ping", get(|| async { "Pong!" }))
+        .route("/ready", get(ready))
+        .route("/metrics", get(metrics))
         .route("/urls", post(shorten))
-        .with_state(shorten_service)
+        .with_state(shorten_service.clone())
         .layer(ServiceBuilder::new().layer(TraceLayer::new_for_http()));
     let listener = TcpListener::bind(format!("{}:{}", config.app.host, config.app.port))
         .await
@@ -62,5 +88,27 @@ async fn main() {
 
     info!("Listening on {}:{}", config.app.host, config.app.port);
 
-    axum::serve(listener, router).await.unwrap();
+    axum::serve(listener, router)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
+
+    // Hand a ZooKeeper shard back right away instead of waiting for the session to expire
+    shorten_service.id_generator.release().await.unwrap();
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async { tokio::signal::ctrl_c().await.unwrap() };
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .unwrap()
+            .recv()
+            .await;
+    };
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("Shutting down...");
 }
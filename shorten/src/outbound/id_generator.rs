@@ -0,0 +1,127 @@
+use crate::{
+    app_config::AppConfig,
+    services::shorten_service::{
+        error::ShortenServiceError,
+        id_generator::{IdCapacity, IdGenerator, IdGeneratorError},
+    },
+};
+
+use super::{
+    mongodb::id_generator::{MongoIdGenerator, MongoIdGeneratorConfig},
+    redis::id_generator::{RedisIdGenerator, RedisIdGeneratorConfig},
+    snowflake::id_generator::{SnowflakeIdGenerator, SnowflakeIdGeneratorConfig},
+    zookeeper::id_generator::ZooKeeperIdGenerator,
+};
+
+/// Which `IdGenerator` the shorten service draws IDs from.
+///
+/// Backends keep separate counters, so don't switch an existing deployment
+/// to another backend without seeding its counter past the IDs already used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdGeneratorBackend {
+    /// Lease blocks from shards claimed in ZooKeeper
+    #[default]
+    #[serde(rename = "zookeeper")]
+    ZooKeeper,
+    /// Lease blocks with `INCRBY` on a Redis counter
+    Redis,
+    /// Lease blocks with `findAndModify` on a MongoDB counter document
+    Mongodb,
+    /// Generate time-based IDs locally from a per-replica worker id
+    Snowflake,
+}
+
+nest! {
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]*
+    pub struct IdGeneratorConfig {
+        #[builder(default)]
+        #[serde(default)]
+        pub backend: IdGeneratorBackend,
+        /// Only the selected backend's section is required
+        pub redis: Option<RedisIdGeneratorConfig>,
+        pub mongodb: Option<MongoIdGeneratorConfig>,
+        pub snowflake: Option<SnowflakeIdGeneratorConfig>,
+    }
+}
+
+/// The `IdGenerator` selected by `IdGeneratorConfig::backend`.
+pub enum AnyIdGenerator {
+    ZooKeeper(ZooKeeperIdGenerator),
+    Redis(RedisIdGenerator),
+    Mongodb(MongoIdGenerator),
+    Snowflake(SnowflakeIdGenerator),
+}
+
+impl IdGenerator for AnyIdGenerator {
+    async fn generate_id(&self) -> Result<String, ShortenServiceError> {
+        match self {
+            AnyIdGenerator::ZooKeeper(generator) => generator.generate_id().await,
+            AnyIdGenerator::Redis(generator) => generator.generate_id().await,
+            AnyIdGenerator::Mongodb(generator) => generator.generate_id().await,
+            AnyIdGenerator::Snowflake(generator) => generator.generate_id().await,
+        }
+    }
+
+    async fn capacity(&self) -> IdCapacity {
+        match self {
+            AnyIdGenerator::ZooKeeper(generator) => generator.capacity().await,
+            AnyIdGenerator::Redis(generator) => generator.capacity().await,
+            AnyIdGenerator::Mongodb(generator) => generator.capacity().await,
+            AnyIdGenerator::Snowflake(generator) => generator.capacity().await,
+        }
+    }
+
+    fn is_degraded(&self) -> bool {
+        match self {
+            AnyIdGenerator::ZooKeeper(generator) => generator.is_degraded(),
+            AnyIdGenerator::Redis(generator) => generator.is_degraded(),
+            AnyIdGenerator::Mongodb(generator) => generator.is_degraded(),
+            AnyIdGenerator::Snowflake(generator) => generator.is_degraded(),
+        }
+    }
+}
+
+impl AnyIdGenerator {
+    pub async fn new(config: &AppConfig) -> Result<Self, IdGeneratorError> {
+        let id_generator = &config.id_generator;
+        info!("Using the {:?} ID generator", id_generator.backend);
+
+        Ok(match id_generator.backend {
+            IdGeneratorBackend::ZooKeeper => AnyIdGenerator::ZooKeeper(
+                ZooKeeperIdGenerator::new(config.zookeeper.clone()).await?,
+            ),
+            IdGeneratorBackend::Redis => {
+                let redis = id_generator
+                    .redis
+                    .clone()
+                    .ok_or(IdGeneratorError::MissingConfig("redis"))?;
+                AnyIdGenerator::Redis(RedisIdGenerator::new(config.redis.clone(), redis).await?)
+            }
+            IdGeneratorBackend::Mongodb => {
+                let mongodb = id_generator
+                    .mongodb
+                    .clone()
+                    .ok_or(IdGeneratorError::MissingConfig("mongodb"))?;
+                AnyIdGenerator::Mongodb(
+                    MongoIdGenerator::new(config.mongodb.clone(), mongodb).await?,
+                )
+            }
+            IdGeneratorBackend::Snowflake => {
+                let snowflake = id_generator
+                    .snowflake
+                    .clone()
+                    .ok_or(IdGeneratorError::MissingConfig("snowflake"))?;
+                AnyIdGenerator::Snowflake(SnowflakeIdGenerator::new(snowflake)?)
+            }
+        })
+    }
+
+    /// Hands back anything the generator holds in its backend, e.g. a ZooKeeper shard.
+    pub async fn release(&self) -> Result<(), IdGeneratorError> {
+        match self {
+            AnyIdGenerator::ZooKeeper(generator) => Ok(generator.release().await?),
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,2 +1,5 @@
+pub mod id_generator;
+pub mod mongodb;
 pub mod redis;
+pub mod snowflake;
 pub mod zookeeper;
@@ -0,0 +1,126 @@
+use std::ops::Range;
+
+use mongodb::{
+    bson::{doc, Document},
+    options::ReturnDocument,
+    Collection,
+};
+use tokio::sync::Mutex;
+use wee_core::outbound::mongodb::MongoConfig;
+
+use crate::services::shorten_service::{
+    error::ShortenServiceError,
+    id_generator::{IdCapacity, IdGenerator, IdGeneratorError},
+};
+
+nest! {
+    #[derive(Debug, thiserror::Error)]*
+    pub enum MongoIdGeneratorError {
+        #[error("MongoDB Client Error: {0}")]
+        ClientError(#[from] mongodb::error::Error),
+
+        #[error("Block size must be greater than 0")]
+        InvalidBlockSize,
+
+        #[error("Internal Error: {0}")]
+        InternalError(#[from] anyhow::Error),
+    }
+}
+
+nest! {
+    #[derive(Debug)]*
+    pub struct MongoIdGenerator {
+        pub config:
+            #[derive(Clone, PartialEq, Serialize, Deserialize, Builder)]*
+            pub struct MongoIdGeneratorConfig {
+                #[builder(into)]
+                pub collection: String,
+                /// The `_id` of the counter document
+                #[builder(into)]
+                pub counter: String,
+                /// How many IDs to lease with each `findAndModify`
+                pub block_size: u64,
+            },
+        pub collection: Collection<Document>,
+        /// The IDs leased to this generator that have not been handed out yet
+        pub lease: Mutex<Range<u64>>,
+    }
+}
+
+impl From<MongoIdGeneratorError> for IdGeneratorError {
+    fn from(err: MongoIdGeneratorError) -> Self {
+        IdGeneratorError::BackendError(err.into())
+    }
+}
+
+impl IdGenerator for MongoIdGenerator {
+    #[instrument(skip(self))]
+    async fn generate_id(&self) -> Result<String, ShortenServiceError> {
+        let mut lease = self.lease.lock().await;
+
+        if lease.is_empty() {
+            *lease = self.lease_block().await.map_err(IdGeneratorError::from)?;
+        }
+
+        let id = lease.next().ok_or(IdGeneratorError::Exhausted)?;
+
+        Ok(id.to_string())
+    }
+
+    async fn capacity(&self) -> IdCapacity {
+        let lease = self.lease.lock().await;
+
+        IdCapacity::builder()
+            .leased(lease.end - lease.start)
+            .remaining(u64::MAX)
+            .exhausted(false)
+            .build()
+    }
+}
+
+impl MongoIdGenerator {
+    pub async fn new(
+        mongo_config: MongoConfig,
+        config: MongoIdGeneratorConfig,
+    ) -> Result<Self, MongoIdGeneratorError> {
+        if config.block_size == 0 {
+            return Err(MongoIdGeneratorError::InvalidBlockSize);
+        }
+
+        let client = mongodb::Client::with_uri_str(&mongo_config.uri()).await?;
+        let collection = client
+            .database(&mongo_config.database)
+            .collection::<Document>(&config.collection);
+
+        Ok(MongoIdGenerator {
+            config,
+            collection,
+            lease: Mutex::new(0..0),
+        })
+    }
+
+    /// Leases the next `block_size` IDs by advancing the counter document atomically.
+    #[instrument(skip(self))]
+    pub async fn lease_block(&self) -> Result<Range<u64>, MongoIdGeneratorError> {
+        let block_size = self.config.block_size as i64;
+        let counter = self
+            .collection
+            .find_one_and_update(
+                doc! {"_id": &self.config.counter},
+                doc! {"$inc": {"next": block_size}},
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Counter {} was not upserted", self.config.counter))?;
+
+        let end = counter
+            .get_i64("next")
+            .map_err(|err| anyhow::anyhow!("Invalid counter {}: {}", self.config.counter, err))?
+            as u64;
+        let lease = end - self.config.block_size..end;
+        info!("Leased IDs {:?}", lease);
+
+        Ok(lease)
+    }
+}
@@ -0,0 +1,109 @@
+use std::ops::Range;
+
+use redis::AsyncCommands;
+use tokio::sync::Mutex;
+use wee_core::outbound::redis::{
+    connection::{RedisConnection, RedisConnectionError},
+    RedisConfig,
+};
+
+use crate::services::shorten_service::{
+    error::ShortenServiceError,
+    id_generator::{IdCapacity, IdGenerator, IdGeneratorError},
+};
+
+nest! {
+    #[derive(Debug, thiserror::Error)]*
+    pub enum RedisIdGeneratorError {
+        #[error("Redis Client Error: {0}")]
+        RedisClientError(#[from] redis::RedisError),
+
+        #[error("Block size must be greater than 0")]
+        InvalidBlockSize,
+
+        #[error("Connection Error: {0}")]
+        ConnectionError(#[from] RedisConnectionError),
+    }
+}
+
+nest! {
+    #[derive(Debug)]*
+    pub struct RedisIdGenerator {
+        pub config:
+            #[derive(Clone, PartialEq, Serialize, Deserialize, Builder)]*
+            pub struct RedisIdGeneratorConfig {
+                /// The key holding the end of the last leased block
+                #[builder(into)]
+                pub key: String,
+                /// How many IDs to lease with each `INCRBY`
+                pub block_size: u64,
+            },
+        pub conn: RedisConnection,
+        /// The IDs leased to this generator that have not been handed out yet
+        pub lease: Mutex<Range<u64>>,
+    }
+}
+
+impl From<RedisIdGeneratorError> for IdGeneratorError {
+    fn from(err: RedisIdGeneratorError) -> Self {
+        IdGeneratorError::BackendError(err.into())
+    }
+}
+
+impl IdGenerator for RedisIdGenerator {
+    #[instrument(skip(self))]
+    async fn generate_id(&self) -> Result<String, ShortenServiceError> {
+        let mut lease = self.lease.lock().await;
+
+        if lease.is_empty() {
+            *lease = self.lease_block().await.map_err(IdGeneratorError::from)?;
+        }
+
+        let id = lease.next().ok_or(IdGeneratorError::Exhausted)?;
+
+        Ok(id.to_string())
+    }
+
+    async fn capacity(&self) -> IdCapacity {
+        let lease = self.lease.lock().await;
+
+        IdCapacity::builder()
+            .leased(lease.end - lease.start)
+            .remaining(u64::MAX)
+            .exhausted(false)
+            .build()
+    }
+}
+
+impl RedisIdGenerator {
+    pub async fn new(
+        redis_config: RedisConfig,
+        config: RedisIdGeneratorConfig,
+    ) -> Result<Self, RedisIdGeneratorError> {
+        if config.block_size == 0 {
+            return Err(RedisIdGeneratorError::InvalidBlockSize);
+        }
+
+        let conn = redis_config.connect("shorten").await?;
+
+        Ok(RedisIdGenerator {
+            config,
+            conn,
+            lease: Mutex::new(0..0),
+        })
+    }
+
+    /// Leases the next `block_size` IDs by advancing the counter atomically.
+    #[instrument(skip(self))]
+    pub async fn lease_block(&self) -> Result<Range<u64>, RedisIdGeneratorError> {
+        let end: u64 = self
+            .conn
+            .clone()
+            .incr(&self.config.key, self.config.block_size)
+            .await?;
+        let lease = end - self.config.block_size..end;
+        info!("Leased IDs {:?}", lease);
+
+        Ok(lease)
+    }
+}
@@ -1 +1,2 @@
+pub mod id_generator;
 pub mod shorten_service_cache;
@@ -0,0 +1,108 @@
+use std::sync::Mutex;
+
+use chrono::Utc;
+
+use crate::services::shorten_service::{
+    error::ShortenServiceError,
+    id_generator::{IdCapacity, IdGenerator, IdGeneratorError},
+};
+
+const WORKER_ID_BITS: u32 = 10;
+const SEQUENCE_BITS: u32 = 12;
+const TIMESTAMP_BITS: u32 = 41;
+pub const MAX_WORKER_ID: u16 = (1 << WORKER_ID_BITS) - 1;
+const MAX_SEQUENCE: u64 = (1 << SEQUENCE_BITS) - 1;
+const MAX_TIMESTAMP: u64 = (1 << TIMESTAMP_BITS) - 1;
+
+nest! {
+    #[derive(Debug, thiserror::Error)]*
+    pub enum SnowflakeIdGeneratorError {
+        #[error("Worker id {0} is above {MAX_WORKER_ID}")]
+        InvalidWorkerId(u16),
+
+        #[error("The epoch is in the future")]
+        EpochInFuture,
+    }
+}
+
+nest! {
+    /// Generates IDs without coordination: 41 bits of milliseconds since `epoch_ms`,
+    /// 10 bits of worker id and a 12-bit sequence within the millisecond.
+    #[derive(Debug)]*
+    pub struct SnowflakeIdGenerator {
+        pub config:
+            #[derive(Clone, PartialEq, Serialize, Deserialize, Builder)]*
+            pub struct SnowflakeIdGeneratorConfig {
+                /// Unique per replica, from 0 to 1023
+                pub worker_id: u16,
+                /// Milliseconds since the Unix epoch that IDs count from
+                pub epoch_ms: u64,
+            },
+        /// The millisecond and sequence of the last ID handed out
+        pub last: Mutex<(u64, u64)>,
+    }
+}
+
+impl From<SnowflakeIdGeneratorError> for IdGeneratorError {
+    fn from(err: SnowflakeIdGeneratorError) -> Self {
+        IdGeneratorError::BackendError(err.into())
+    }
+}
+
+impl IdGenerator for SnowflakeIdGenerator {
+    #[instrument(skip(self))]
+    async fn generate_id(&self) -> Result<String, ShortenServiceError> {
+        Ok(self.next_id()?.to_string())
+    }
+
+    async fn capacity(&self) -> IdCapacity {
+        let elapsed = self.elapsed_ms().unwrap_or(MAX_TIMESTAMP);
+
+        IdCapacity::builder()
+            .leased(0)
+            .remaining((MAX_TIMESTAMP.saturating_sub(elapsed)) << SEQUENCE_BITS)
+            .exhausted(elapsed >= MAX_TIMESTAMP)
+            .build()
+    }
+}
+
+impl SnowflakeIdGenerator {
+    pub fn new(config: SnowflakeIdGeneratorConfig) -> Result<Self, SnowflakeIdGeneratorError> {
+        if config.worker_id > MAX_WORKER_ID {
+            return Err(SnowflakeIdGeneratorError::InvalidWorkerId(config.worker_id));
+        }
+
+        Ok(SnowflakeIdGenerator {
+            config,
+            last: Mutex::new((0, 0)),
+        })
+    }
+
+    pub fn next_id(&self) -> Result<u64, IdGeneratorError> {
+        let now = self.elapsed_ms()?;
+        let mut last = self.last.lock().unwrap();
+
+        // Never go back in time: if the clock does, or a millisecond runs out of
+        // sequence numbers, keep counting from the last millisecond handed out
+        *last = match *last {
+            (ms, sequence) if now <= ms && sequence < MAX_SEQUENCE => (ms, sequence + 1),
+            (ms, _) if now <= ms => (ms + 1, 0),
+            _ => (now, 0),
+        };
+        let (ms, sequence) = *last;
+
+        if ms > MAX_TIMESTAMP {
+            return Err(IdGeneratorError::Exhausted);
+        }
+
+        Ok((ms << (WORKER_ID_BITS + SEQUENCE_BITS))
+            | (u64::from(self.config.worker_id) << SEQUENCE_BITS)
+            | sequence)
+    }
+
+    fn elapsed_ms(&self) -> Result<u64, SnowflakeIdGeneratorError> {
+        (Utc::now().timestamp_millis() as u64)
+            .checked_sub(self.config.epoch_ms)
+            .ok_or(SnowflakeIdGeneratorError::EpochInFuture)
+    }
+}
@@ -0,0 +1 @@
+pub mod id_generator;
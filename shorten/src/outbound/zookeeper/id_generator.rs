@@ -1,10 +1,18 @@
-use std::net::ToSocketAddrs;
+use std::{
+    net::ToSocketAddrs,
+    ops::Range,
+    sync::{atomic::Ordering, Arc},
+};
 
+use tokio::sync::Mutex;
 use tokio_zookeeper::{self as tzk, Acl, CreateMode};
 
-use crate::services::shorten_service::{error::ShortenServiceError, id_generator::IdGenerator};
+use crate::services::shorten_service::{
+    error::ShortenServiceError,
+    id_generator::{IdCapacity, IdGenerator, IdGeneratorError},
+};
 
-use super::ZooKeeperConfig;
+use super::{session::ZooKeeperSession, ZooKeeperConfig};
 
 nest! {
     #[derive(Debug, thiserror::Error)]*
@@ -15,9 +23,18 @@ nest! {
         #[error("ZooKeeperIdGenerator Create Error: {0}")]
         CreateError(#[from] tzk::error::Create),
 
+        #[error("ZooKeeperIdGenerator SetData Error: {0}")]
+        SetDataError(#[from] tzk::error::SetData),
+
+        #[error("ZooKeeperIdGenerator Delete Error: {0}")]
+        DeleteError(#[from] tzk::error::Delete),
+
         #[error("Id Not Found")]
         IdNotFound,
 
+        #[error("Shard Exhausted: every shard is used up or owned by another replica")]
+        ShardExhausted,
+
         #[error("Internal Error: {0}")]
         InternalError(#[from] anyhow::Error),
     }
@@ -28,77 +45,241 @@ nest! {
         pub config:
             #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]*
             pub struct ZooKeeperIdGeneratorConfig {
-                pub shard_info:
-                    pub struct ShardInfo {
-                        #[builder(into)]
-                        pub base_path: String,
-                        pub id: usize,
-                        pub start: usize,
-                        pub end: usize,
-                    },
+                #[builder(into)]
+                pub base_path: String,
+                /// How many IDs each shard holds; must not change once IDs were handed out
+                pub shard_size: usize,
+                /// How many shards replicas may claim
+                pub shard_count: usize,
+                /// How many IDs to lease from the shard counter at a time
+                pub block_size: usize,
             },
-        pub client: tzk::ZooKeeper,
+        pub session: Arc<ZooKeeperSession>,
+        /// The claimed shard and the IDs leased from it that have not been handed out yet
+        pub lease: Mutex<Lease>,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lease {
+    pub shard_info: ShardInfo,
+    /// The whole block last leased, whose `lease-*` node goes once it is used up
+    pub block: Range<usize>,
+    /// The IDs of `block` that have not been handed out yet
+    pub ids: Range<usize>,
+    /// IDs of the shard that nobody has leased yet
+    pub unleased: usize,
+    /// IDs of the shards no replica has started on yet, as of the last lease
+    pub unclaimed: usize,
+    /// The session holding the shard's owner node, as its `ephemeralOwner` reads
+    pub owner_session: i64,
+    /// Cleared when another replica took the shard while our session was down;
+    /// the leased IDs stay ours, but no more blocks are leased from the shard
+    pub owned: bool,
+    /// Set while no shard is claimed, after the last one ran out or was lost; the
+    /// next call tries to claim one again
+    pub exhausted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
+pub struct ShardInfo {
+    #[builder(into)]
+    pub base_path: String,
+    pub id: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl ZooKeeperIdGeneratorConfig {
+    pub fn shard_info(&self, id: usize) -> ShardInfo {
+        ShardInfo::builder()
+            .base_path(self.base_path.clone())
+            .id(id)
+            .start(id * self.shard_size)
+            .end((id + 1) * self.shard_size - 1)
+            .build()
     }
 
+    /// Holds how many shards, counted from shard 0, are known to have no IDs left.
+    /// Shards are used up in order, so claims start scanning there.
+    pub fn exhausted_path(&self) -> String {
+        format!("/{}/exhausted", self.base_path)
+    }
 }
 
 impl ShardInfo {
-    pub fn id_path(&self) -> String {
+    pub fn shard_path(&self) -> String {
+        format!("/{}/{}", self.base_path, self.name())
+    }
+
+    /// The shard's node name under the base path.
+    pub fn name(&self) -> String {
+        format!("shard-{}-{}-{}", self.id, self.start, self.end)
+    }
+
+    /// The id and start of a shard from its node name, in this layout or an earlier one.
+    fn parse_name(name: &str) -> Option<(usize, usize)> {
+        let parts = name
+            .strip_prefix("shard-")?
+            .split('-')
+            .map(str::parse::<usize>)
+            .collect::<Vec<_>>();
+
+        match parts[..] {
+            [Ok(id), Ok(start), Ok(_)] => Some((id, start)),
+            _ => None,
+        }
+    }
+
+    /// An ephemeral node held by the replica that claimed the shard.
+    pub fn owner_path(&self) -> String {
+        format!("{}/owner", self.shard_path())
+    }
+
+    /// Holds the offset of the next unleased ID in the shard.
+    pub fn counter_path(&self) -> String {
+        format!("{}/counter", self.shard_path())
+    }
+
+    /// Holds one child per leased block that has not been used up yet.
+    pub fn leases_path(&self) -> String {
+        format!("{}/leases", self.shard_path())
+    }
+
+    pub fn lease_path(&self, lease: &Range<usize>) -> String {
         format!(
-            "/{}/shard-{}-{}-{}/",
-            self.base_path, self.id, self.start, self.end
+            "{}/lease-{}-{}",
+            self.leases_path(),
+            lease.start,
+            lease.end - 1
         )
     }
+
+    pub fn capacity(&self) -> usize {
+        self.end - self.start + 1
+    }
+}
+
+impl From<ZooKeeperIdGeneratorError> for IdGeneratorError {
+    fn from(err: ZooKeeperIdGeneratorError) -> Self {
+        match err {
+            ZooKeeperIdGeneratorError::ShardExhausted => IdGeneratorError::Exhausted,
+            err => IdGeneratorError::BackendError(err.into()),
+        }
+    }
 }
 
 impl IdGenerator for ZooKeeperIdGenerator {
     #[instrument(skip(self))]
     async fn generate_id(&self) -> Result<String, ShortenServiceError> {
-        let path = self
-            .client
-            .create(
-                &self.config.shard_info.id_path(),
-                b"",
-                Acl::open_unsafe(),
-                CreateMode::PersistentSequential,
-            )
+        self.next_id()
             .await
-            .map_err(ZooKeeperIdGeneratorError::ClientError)?
-            .map_err(ZooKeeperIdGeneratorError::CreateError)?;
+            .map(|id| id.to_string())
+            .map_err(|err| IdGeneratorError::from(err).into())
+    }
 
-        tracing::debug!("Created sequential node: {}", path);
+    async fn capacity(&self) -> IdCapacity {
+        let mut lease = self.lease.lock().await;
 
-        let id = path
-            .split('/')
-            .next_back()
-            .ok_or_else(|| {
-                ZooKeeperIdGeneratorError::InternalError(anyhow::anyhow!(
-                    "Failed to parse ID from path: {}",
-                    path
-                ))
-            })?
-            .to_string()
-            .parse::<usize>()
-            .map_err(|err| {
-                ZooKeeperIdGeneratorError::InternalError(anyhow::anyhow!(
-                    "Failed to parse ID to usize: {}",
-                    err
-                ))
-            })?;
+        if lease.exhausted {
+            match self.claim_shard().await {
+                Ok(claimed) => *lease = claimed,
+                Err(ZooKeeperIdGeneratorError::ShardExhausted) => {}
+                Err(err) => error!("Failed to claim a shard: {}", err),
+            }
+        }
 
-        let global_id = self.config.shard_info.start + id;
+        IdCapacity::builder()
+            .leased(lease.ids.len() as u64)
+            .remaining((lease.ids.len() + lease.unleased + lease.unclaimed) as u64)
+            .exhausted(lease.exhausted)
+            .build()
+    }
 
-        // If global_id is greater than self.config.shard_info.end, gracefully shutdown the service
-        if global_id > self.config.shard_info.end {
-            info!("Global ID is greater than the end of the shard");
-            std::process::exit(1);
-        }
+    fn is_degraded(&self) -> bool {
+        !self.session.is_connected()
+    }
+}
 
-        Ok(global_id.to_string())
+impl Lease {
+    fn from_shard(
+        shard_info: ShardInfo,
+        owner_session: i64,
+        unleased: usize,
+        unclaimed: usize,
+    ) -> Self {
+        let start = shard_info.start;
+        Lease {
+            shard_info,
+            block: start..start,
+            ids: start..start,
+            unleased,
+            unclaimed,
+            owner_session,
+            owned: true,
+            exhausted: false,
+        }
     }
 }
 
 impl ZooKeeperIdGenerator {
+    /// Hands out the next leased ID, leasing a new block or claiming a new
+    /// shard when needed.
+    #[instrument(skip(self))]
+    pub async fn next_id(&self) -> Result<usize, ZooKeeperIdGeneratorError> {
+        let mut lease = self.lease.lock().await;
+
+        if self.session.take_renewed() {
+            if let Err(err) = self.restore_ownership(&mut lease).await {
+                self.session.renewed.store(true, Ordering::SeqCst);
+                return Err(err);
+            }
+        }
+
+        while lease.ids.is_empty() {
+            // Another replica may have released a shard since
+            if lease.exhausted {
+                *lease = self.claim_shard().await?;
+            }
+
+            let used = lease.clone();
+            let leased = if lease.owned {
+                self.lease_block(&lease.shard_info).await?
+            } else {
+                None
+            };
+
+            match leased {
+                Some((ids, unleased)) => {
+                    lease.block = ids.clone();
+                    lease.ids = ids;
+                    lease.unleased = unleased;
+                    lease.unclaimed = self.unclaimed_ids().await?;
+                }
+                None => {
+                    if lease.owned {
+                        info!("Shard {} is exhausted", lease.shard_info.id);
+                        self.release_shard(&lease.shard_info, lease.owner_session)
+                            .await?;
+                        self.mark_exhausted(lease.shard_info.id).await?;
+                    } else {
+                        info!("Shard {} was lost", lease.shard_info.id);
+                    }
+                    lease.unclaimed = 0;
+                    lease.exhausted = true;
+                }
+            }
+
+            self.release_block(&used.shard_info, &used.block).await?;
+        }
+
+        let id = lease.ids.next().ok_or_else(|| {
+            ZooKeeperIdGeneratorError::InternalError(anyhow::anyhow!("Leased an empty block"))
+        })?;
+
+        Ok(id)
+    }
+
     pub async fn new(config: ZooKeeperConfig) -> Result<Self, ZooKeeperIdGeneratorError> {
         let address = (config.host, config.port)
             .to_socket_addrs()
@@ -114,37 +295,276 @@ impl ZooKeeperIdGenerator {
             })?;
         info!("Address: {}", address);
 
-        let (client, _watcher) = tzk::ZooKeeper::connect(&address).await?;
+        let session = ZooKeeperSession::connect(address).await?;
 
-        let zk = ZooKeeperIdGenerator {
+        let mut zk = ZooKeeperIdGenerator {
             config: config.id_generator.clone(),
-            client,
+            session,
+            lease: Mutex::new(Lease::from_shard(
+                config.id_generator.shard_info(0),
+                0,
+                0,
+                0,
+            )),
         };
-        zk.ensure_shard_path_exist().await?;
+        zk.lease = Mutex::new(zk.claim_shard().await?);
 
         Ok(zk)
     }
 
+    /// Claims the first shard that has IDs left and no owner, and returns an
+    /// empty lease on it.
+    ///
+    /// Ownership is an ephemeral node, so a shard is freed when its replica
+    /// releases it or its ZooKeeper session ends. The scan starts after the shards
+    /// `exhausted_path` counts as used up, and a shard is only created once reached,
+    /// so a claim takes one round trip per owned shard it passes.
     #[instrument(skip(self))]
-    pub async fn ensure_shard_path_exist(&self) -> Result<(), ZooKeeperIdGeneratorError> {
-        let nodes = format!(
-            "{}/shard-{}-{}-{}",
-            self.config.shard_info.base_path,
-            self.config.shard_info.id,
-            self.config.shard_info.start,
-            self.config.shard_info.end
-        )
-        .split("/")
-        .map(|s| s.to_string())
-        .collect::<Vec<_>>();
+    pub async fn claim_shard(&self) -> Result<Lease, ZooKeeperIdGeneratorError> {
+        for id in self.exhausted_shards().await?..self.config.shard_count {
+            let shard_info = self.config.shard_info(id);
+
+            let claimed = match self.create_owner(&shard_info).await? {
+                Err(tzk::error::Create::NoNode) => {
+                    self.ensure_shard_path_exist(&shard_info).await?;
+                    self.create_owner(&shard_info).await?
+                }
+                claimed => claimed,
+            };
+            let owner_session = match claimed {
+                Ok(owner_session) => owner_session,
+                Err(tzk::error::Create::NodeExists) => {
+                    debug!("Shard {} is owned by another replica", id);
+                    continue;
+                }
+                Err(create_err) => return Err(create_err.into()),
+            };
+
+            let next = match self.next_offset(&shard_info).await {
+                // A replica stopped while creating the shard
+                Err(ZooKeeperIdGeneratorError::IdNotFound) => {
+                    self.ensure_shard_path_exist(&shard_info).await?;
+                    self.next_offset(&shard_info).await?
+                }
+                next => next?,
+            };
+            if next.0 >= shard_info.capacity() {
+                self.release_shard(&shard_info, owner_session).await?;
+                self.mark_exhausted(id).await?;
+                continue;
+            }
+
+            info!("Claimed shard {:?}", shard_info);
+            let unleased = shard_info.capacity() - next.0;
+            let unclaimed = self.unclaimed_ids().await?;
+            return Ok(Lease::from_shard(
+                shard_info,
+                owner_session,
+                unleased,
+                unclaimed,
+            ));
+        }
+
+        error!("Every shard is exhausted or owned by another replica");
+        Err(ZooKeeperIdGeneratorError::ShardExhausted)
+    }
+
+    /// Creates the shard's ephemeral owner node and returns the id of the session
+    /// holding it. The client doesn't expose its session id, so it is read back
+    /// from the node's `ephemeralOwner`.
+    async fn create_owner(
+        &self,
+        shard_info: &ShardInfo,
+    ) -> Result<Result<i64, tzk::error::Create>, ZooKeeperIdGeneratorError> {
+        let client = self.session.client().await;
+        if let Err(create_err) = client
+            .create(
+                &shard_info.owner_path(),
+                std::process::id().to_string().into_bytes(),
+                Acl::open_unsafe(),
+                CreateMode::Ephemeral,
+            )
+            .await?
+        {
+            return Ok(Err(create_err));
+        }
+
+        let stat = client
+            .exists(&shard_info.owner_path())
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Owner of shard {} vanished", shard_info.id))?;
+
+        Ok(Ok(stat.ephemeral_owner))
+    }
+
+    /// How many IDs the shards no replica has started on yet hold. Shards are only
+    /// created once a claim reaches them, so those are the ones without a node.
+    async fn unclaimed_ids(&self) -> Result<usize, ZooKeeperIdGeneratorError> {
+        let started = self
+            .session
+            .client()
+            .await
+            .get_children(&format!("/{}", self.config.base_path))
+            .await?
+            .unwrap_or_default()
+            .iter()
+            .filter(|name| {
+                ShardInfo::parse_name(name).is_some_and(|(id, _)| {
+                    id < self.config.shard_count && **name == self.config.shard_info(id).name()
+                })
+            })
+            .count();
+
+        Ok((self.config.shard_count - started) * self.config.shard_size)
+    }
+
+    /// How many shards, counted from shard 0, are known to have no IDs left.
+    async fn exhausted_shards(&self) -> Result<usize, ZooKeeperIdGeneratorError> {
+        match self
+            .session
+            .client()
+            .await
+            .get_data(&self.config.exhausted_path())
+            .await?
+        {
+            Some((data, _)) => Ok(String::from_utf8(data)
+                .map_err(anyhow::Error::from)?
+                .parse::<usize>()
+                .map_err(anyhow::Error::from)?),
+            None => Ok(0),
+        }
+    }
+
+    /// Moves the start of later claims past shard `id` once every shard before it is
+    /// used up. Losing a race to another replica is fine, the next scan catches up.
+    async fn mark_exhausted(&self, id: usize) -> Result<(), ZooKeeperIdGeneratorError> {
+        let path = self.config.exhausted_path();
+        let data = (id + 1).to_string().into_bytes();
+
+        match self.session.client().await.get_data(&path).await? {
+            Some((current, stat)) if current == id.to_string().into_bytes() => {
+                match self
+                    .session
+                    .client()
+                    .await
+                    .set_data(&path, Some(stat.version), data)
+                    .await?
+                {
+                    Ok(_) | Err(tzk::error::SetData::BadVersion { .. }) => {}
+                    Err(set_data_err) => return Err(set_data_err.into()),
+                }
+            }
+            None if id == 0 => {
+                match self
+                    .session
+                    .client()
+                    .await
+                    .create(&path, data, Acl::open_unsafe(), CreateMode::Persistent)
+                    .await?
+                {
+                    Ok(_) | Err(tzk::error::Create::NodeExists) => {}
+                    Err(create_err) => return Err(create_err.into()),
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Gives up the claimed shard, e.g. on shutdown, so another replica can take it.
+    #[instrument(skip(self))]
+    pub async fn release(&self) -> Result<(), ZooKeeperIdGeneratorError> {
+        let lease = self.lease.lock().await;
+        if lease.owned && !lease.exhausted {
+            self.release_shard(&lease.shard_info, lease.owner_session)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Re-checks the shard paths and reclaims the shard after a new session
+    /// replaced an expired one, whose ephemeral owner node is gone.
+    ///
+    /// The server may not have expired the old session yet, so an owner node it
+    /// still holds is ours and gets replaced, not taken for another replica's claim.
+    #[instrument(skip(self, lease))]
+    async fn restore_ownership(&self, lease: &mut Lease) -> Result<(), ZooKeeperIdGeneratorError> {
+        if !lease.owned || lease.exhausted {
+            return Ok(());
+        }
+
+        self.ensure_shard_path_exist(&lease.shard_info).await?;
+        let mut claimed = self.create_owner(&lease.shard_info).await?;
+        if claimed == Err(tzk::error::Create::NodeExists)
+            && self
+                .release_shard(&lease.shard_info, lease.owner_session)
+                .await?
+        {
+            claimed = self.create_owner(&lease.shard_info).await?;
+        }
+
+        match claimed {
+            Ok(owner_session) => {
+                info!("Reclaimed shard {}", lease.shard_info.id);
+                lease.owner_session = owner_session;
+            }
+            Err(tzk::error::Create::NodeExists) => {
+                warn!(
+                    "Shard {} was claimed by another replica, finishing the leased block",
+                    lease.shard_info.id
+                );
+                lease.owned = false;
+                lease.unleased = 0;
+            }
+            Err(create_err) => return Err(create_err.into()),
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the shard's owner node if `owner_session` still holds it, and returns
+    /// whether it did. A node another replica created after ours expired is kept.
+    async fn release_shard(
+        &self,
+        shard_info: &ShardInfo,
+        owner_session: i64,
+    ) -> Result<bool, ZooKeeperIdGeneratorError> {
+        let client = self.session.client().await;
+        let owner_path = shard_info.owner_path();
+
+        let Some(stat) = client.exists(&owner_path).await? else {
+            return Ok(false);
+        };
+        if stat.ephemeral_owner != owner_session {
+            info!("Shard {} is owned by another replica now", shard_info.id);
+            return Ok(false);
+        }
+
+        match client.delete(&owner_path, Some(stat.version)).await? {
+            Ok(()) => {
+                info!("Released shard {}", shard_info.id);
+                Ok(true)
+            }
+            Err(tzk::error::Delete::NoNode | tzk::error::Delete::BadVersion { .. }) => Ok(false),
+            Err(delete_err) => Err(delete_err.into()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn ensure_shard_path_exist(
+        &self,
+        shard_info: &ShardInfo,
+    ) -> Result<(), ZooKeeperIdGeneratorError> {
+        let client = self.session.client().await;
         let mut path = String::new();
 
-        for node in nodes {
+        for node in shard_info.shard_path().split("/").skip(1) {
             path = format!("{}/{}", path, node);
-            if self.client.watch().exists(&path).await?.is_none() {
+            if client.watch().exists(&path).await?.is_none() {
                 // Skip if the path already exists
-                let create = self
-                    .client
+                let create = client
                     .create(&path, b"", Acl::open_unsafe(), CreateMode::Persistent)
                     .await?;
 
@@ -158,6 +578,184 @@ impl ZooKeeperIdGenerator {
             }
         }
 
+        for path in [shard_info.counter_path(), shard_info.leases_path()] {
+            if client.exists(&path).await?.is_some() {
+                continue;
+            }
+
+            let data = if path == shard_info.counter_path() {
+                self.legacy_offset(shard_info)
+                    .await?
+                    .to_string()
+                    .into_bytes()
+            } else {
+                Vec::new()
+            };
+
+            match client
+                .create(&path, data, Acl::open_unsafe(), CreateMode::Persistent)
+                .await?
+            {
+                Ok(path) => info!("Created path: {}", path),
+                Err(tzk::error::Create::NodeExists) => info!("Path already exists: {}", path),
+                Err(create_err) => return Err(ZooKeeperIdGeneratorError::CreateError(create_err)),
+            }
+        }
+
         Ok(())
     }
+
+    /// The offset right after the highest ID earlier versions handed out in the shard's
+    /// range, or 0 for a new range. A new counter starts there, so those IDs are never
+    /// handed out again.
+    ///
+    /// Earlier versions created one sequential child per ID under a statically configured
+    /// `shard-{id}-{start}-{end}` node, whose range need not match the current layout.
+    /// Every such node under the base path is mapped onto the shards it overlaps.
+    #[instrument(skip(self))]
+    async fn legacy_offset(
+        &self,
+        shard_info: &ShardInfo,
+    ) -> Result<usize, ZooKeeperIdGeneratorError> {
+        let shards = self
+            .session
+            .client()
+            .await
+            .get_children(&format!("/{}", self.config.base_path))
+            .await?
+            .unwrap_or_default();
+
+        let mut offset = 0;
+        for name in shards {
+            let Some((id, start)) = ShardInfo::parse_name(&name) else {
+                continue;
+            };
+            // Other shards of the current layout only hold a counter
+            if id != shard_info.id && name == self.config.shard_info(id).name() {
+                continue;
+            }
+
+            let highest = self
+                .session
+                .client()
+                .await
+                .get_children(&format!("/{}/{}", self.config.base_path, name))
+                .await?
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|child| child.parse::<usize>().ok())
+                .max()
+                .map(|seq| start + seq);
+            if let Some(highest) = highest.filter(|highest| *highest >= shard_info.start) {
+                info!(
+                    "{} handed out IDs up to {} in earlier versions",
+                    name, highest
+                );
+                offset = offset.max(highest.min(shard_info.end) - shard_info.start + 1);
+            }
+        }
+
+        Ok(offset)
+    }
+
+    /// Reads the shard counter, returning the next unleased offset and the counter's version.
+    async fn next_offset(
+        &self,
+        shard_info: &ShardInfo,
+    ) -> Result<(usize, i32), ZooKeeperIdGeneratorError> {
+        let (data, stat) = self
+            .session
+            .client()
+            .await
+            .get_data(&shard_info.counter_path())
+            .await?
+            .ok_or(ZooKeeperIdGeneratorError::IdNotFound)?;
+        let next = String::from_utf8(data)
+            .map_err(anyhow::Error::from)?
+            .parse::<usize>()
+            .map_err(anyhow::Error::from)?;
+
+        Ok((next, stat.version))
+    }
+
+    /// Leases the next `block_size` IDs of the shard and records the lease, or
+    /// returns `None` once the shard has no IDs left. Also returns how many IDs
+    /// of the shard remain unleased.
+    ///
+    /// The counter is advanced with a versioned write, so generators sharing a
+    /// shard never lease overlapping blocks. A generator that crashes loses at
+    /// most the rest of its current block, which stays listed under `leases`.
+    #[instrument(skip(self))]
+    pub async fn lease_block(
+        &self,
+        shard_info: &ShardInfo,
+    ) -> Result<Option<(Range<usize>, usize)>, ZooKeeperIdGeneratorError> {
+        loop {
+            let (next, version) = self.next_offset(shard_info).await?;
+            if next >= shard_info.capacity() {
+                return Ok(None);
+            }
+
+            let end = (next + self.config.block_size).min(shard_info.capacity());
+            match self
+                .session
+                .client()
+                .await
+                .set_data(
+                    &shard_info.counter_path(),
+                    Some(version),
+                    end.to_string().into_bytes(),
+                )
+                .await?
+            {
+                Ok(_) => {
+                    let lease = shard_info.start + next..shard_info.start + end;
+                    self.session
+                        .client()
+                        .await
+                        .create(
+                            &shard_info.lease_path(&lease),
+                            b"",
+                            Acl::open_unsafe(),
+                            CreateMode::Persistent,
+                        )
+                        .await??;
+                    info!("Leased IDs {:?}", lease);
+
+                    let unleased = shard_info.capacity() - end;
+                    if unleased < shard_info.capacity() / 10 {
+                        warn!("Shard {} has {} unleased IDs left", shard_info.id, unleased);
+                    }
+
+                    return Ok(Some((lease, unleased)));
+                }
+                Err(tzk::error::SetData::BadVersion { .. }) => {
+                    debug!("Counter changed while leasing, retrying");
+                }
+                Err(set_data_err) => return Err(set_data_err.into()),
+            }
+        }
+    }
+
+    /// Forgets a block once every ID in it has been handed out.
+    async fn release_block(
+        &self,
+        shard_info: &ShardInfo,
+        lease: &Range<usize>,
+    ) -> Result<(), ZooKeeperIdGeneratorError> {
+        if lease.start == lease.end {
+            return Ok(());
+        }
+
+        match self
+            .session
+            .client()
+            .await
+            .delete(&shard_info.lease_path(lease), None)
+            .await?
+        {
+            Ok(()) | Err(tzk::error::Delete::NoNode) => Ok(()),
+            Err(delete_err) => Err(delete_err.into()),
+        }
+    }
 }
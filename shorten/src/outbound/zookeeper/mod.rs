@@ -1,6 +1,7 @@
 use id_generator::ZooKeeperIdGeneratorConfig;
 
 pub mod id_generator;
+pub mod session;
 
 nest! {
     #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]*
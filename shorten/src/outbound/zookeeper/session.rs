@@ -0,0 +1,107 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures_util::{stream::BoxStream, StreamExt};
+use tokio::sync::RwLock;
+use tokio_zookeeper::{self as tzk, KeeperState, WatchedEvent};
+
+const MIN_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// A ZooKeeper client that opens a new session whenever the current one expires.
+///
+/// A background task follows the session's watcher stream: it tracks whether the
+/// client is connected, and reconnects with exponential backoff once the session
+/// expires or the stream ends.
+#[derive(Debug)]
+pub struct ZooKeeperSession {
+    pub address: SocketAddr,
+    pub client: RwLock<tzk::ZooKeeper>,
+    pub connected: AtomicBool,
+    /// Set when a new session replaced an expired one, until the owner takes it
+    pub renewed: AtomicBool,
+}
+
+impl ZooKeeperSession {
+    pub async fn connect(address: SocketAddr) -> Result<Arc<Self>, tzk::error::Error> {
+        let (client, watcher) = tzk::ZooKeeper::connect(&address).await?;
+
+        let session = Arc::new(ZooKeeperSession {
+            address,
+            client: RwLock::new(client),
+            connected: AtomicBool::new(true),
+            renewed: AtomicBool::new(false),
+        });
+        tokio::spawn(session.clone().watch(watcher.boxed()));
+
+        Ok(session)
+    }
+
+    pub async fn client(&self) -> tzk::ZooKeeper {
+        self.client.read().await.clone()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    /// Returns whether the session was renewed since the last call.
+    pub fn take_renewed(&self) -> bool {
+        self.renewed.swap(false, Ordering::SeqCst)
+    }
+
+    async fn watch(self: Arc<Self>, mut watcher: BoxStream<'static, WatchedEvent>) {
+        loop {
+            while let Some(event) = watcher.next().await {
+                match event.keeper_state {
+                    KeeperState::Disconnected => {
+                        warn!("Disconnected from ZooKeeper at {}", self.address);
+                        self.connected.store(false, Ordering::SeqCst);
+                    }
+                    KeeperState::SyncConnected | KeeperState::ConnectedReadOnly => {
+                        if !self.connected.swap(true, Ordering::SeqCst) {
+                            info!("Connection to ZooKeeper restored");
+                        }
+                    }
+                    KeeperState::Expired => break,
+                    _ => debug!("ZooKeeper event: {:?}", event),
+                }
+            }
+
+            warn!("ZooKeeper session ended, reconnecting");
+            self.connected.store(false, Ordering::SeqCst);
+            watcher = self.reconnect().await;
+        }
+    }
+
+    async fn reconnect(&self) -> BoxStream<'static, WatchedEvent> {
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            match tzk::ZooKeeper::connect(&self.address).await {
+                Ok((client, watcher)) => {
+                    *self.client.write().await = client;
+                    self.renewed.store(true, Ordering::SeqCst);
+                    self.connected.store(true, Ordering::SeqCst);
+                    info!("Opened a new ZooKeeper session");
+
+                    return watcher.boxed();
+                }
+                Err(err) => {
+                    warn!(
+                        "Failed to reconnect to ZooKeeper, retrying in {:?}: {}",
+                        backoff, err
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::Utc;
+use wee_core::domain::{entities::url::Url, repos::url_repo::UrlRepo};
+
+use super::shorten_service::{cache::ShortenServiceCache, error::ShortenServiceError};
+
+nest! {
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]*
+    pub struct ExpirationSweeperConfig {
+        pub enabled: bool,
+        pub interval_secs: u64,
+        /// How many expired URLs are deleted per repository call. It can't be 0, which
+        /// some repositories would take as no limit at all.
+        pub batch_size: NonZeroU32,
+    }
+}
+
+/// Periodically deletes expired URLs and evicts them from the cache, which keeps
+/// storage bounded and frees their aliases for reuse.
+pub struct ExpirationSweeper<R: UrlRepo, C: ShortenServiceCache> {
+    pub config: ExpirationSweeperConfig,
+    pub repository: Arc<R>,
+    pub cache: Arc<C>,
+    /// Deleted URLs whose eviction failed, retried on the next sweep
+    pending_evictions: Mutex<Vec<Url>>,
+}
+
+impl<R: UrlRepo, C: ShortenServiceCache> ExpirationSweeper<R, C> {
+    pub fn new(config: ExpirationSweeperConfig, repository: Arc<R>, cache: Arc<C>) -> Self {
+        ExpirationSweeper {
+            config,
+            repository,
+            cache,
+            pending_evictions: Mutex::default(),
+        }
+    }
+
+    /// Runs one sweep and returns how many URLs were removed. A URL whose eviction
+    /// fails is already deleted, so it is kept aside and evicted again next time.
+    #[instrument(skip(self))]
+    pub async fn sweep(&self) -> Result<usize, ShortenServiceError> {
+        let now = Utc::now();
+        let batch_size = self.config.batch_size.get();
+        let mut failed = vec![];
+        let mut removed = 0;
+
+        let pending = std::mem::take(&mut *self.pending_evictions.lock().unwrap());
+        self.evict_all(pending, &mut failed).await;
+
+        loop {
+            let expired = match self.repository.delete_expired(now, batch_size).await {
+                Ok(expired) => expired,
+                Err(err) => {
+                    self.pending_evictions.lock().unwrap().extend(failed);
+                    return Err(err.into());
+                }
+            };
+
+            removed += expired.len();
+            let done = expired.len() < batch_size as usize;
+            self.evict_all(expired, &mut failed).await;
+
+            if done {
+                break;
+            }
+        }
+
+        if !failed.is_empty() {
+            warn!("{} expired URLs are left to evict", failed.len());
+        }
+        self.pending_evictions.lock().unwrap().extend(failed);
+
+        info!("Removed {} expired URLs", removed);
+
+        Ok(removed)
+    }
+
+    async fn evict_all(&self, urls: Vec<Url>, failed: &mut Vec<Url>) {
+        for url in urls {
+            if let Err(err) = self.cache.evict(&url).await {
+                error!("Failed to evict expired URL {}: {}", url.short, err);
+                failed.push(url);
+            }
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.interval_secs));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = self.sweep().await {
+                error!("Expiration sweep failed: {}", err);
+            }
+        }
+    }
+}
@@ -1 +1,2 @@
+pub mod expiration_sweeper;
 pub mod shorten_service;
@@ -17,4 +17,7 @@ pub trait ShortenServiceCache: Send + Sync {
     ) -> impl Future<Output = Result<Option<Url>, ShortenServiceError>> + Send;
 
     fn cache(&self, url: &Url) -> impl Future<Output = Result<(), ShortenServiceError>> + Send;
+
+    /// Removes every entry written by `cache` for this URL.
+    fn evict(&self, url: &Url) -> impl Future<Output = Result<(), ShortenServiceError>> + Send;
 }
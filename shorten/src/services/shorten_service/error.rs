@@ -1,9 +1,8 @@
 use wee_core::domain::repos::url_repo::UrlRepoError;
 
-use crate::outbound::{
-    redis::shorten_service_cache::RedisShortenServiceCacheError,
-    zookeeper::id_generator::ZooKeeperIdGeneratorError,
-};
+use crate::outbound::redis::shorten_service_cache::RedisShortenServiceCacheError;
+
+use super::id_generator::IdGeneratorError;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ShortenServiceError {
@@ -11,7 +10,7 @@ pub enum ShortenServiceError {
     UrlRepoError(#[from] UrlRepoError),
 
     #[error("Generate Id Error: {0}")]
-    IdGeneratorError(#[from] ZooKeeperIdGeneratorError),
+    IdGeneratorError(#[from] IdGeneratorError),
 
     #[error("Cache Error: {0}")]
     CacheError(#[from] RedisShortenServiceCacheError),
@@ -19,6 +18,9 @@ pub enum ShortenServiceError {
     #[error("Internal Error: {0}")]
     InternalError(#[from] Box<dyn std::error::Error + Send + Sync>),
 
+    #[error("Alias could be mistaken for a short code: {0}")]
+    InvalidAlias(String),
+
     #[error("Alias already taken: {0}")]
     AliasTaken(String),
 
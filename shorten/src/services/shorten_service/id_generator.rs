@@ -2,6 +2,38 @@ use std::future::Future;
 
 use super::error::ShortenServiceError;
 
+/// The errors every `IdGenerator` backend reports in.
+#[derive(Debug, thiserror::Error)]
+pub enum IdGeneratorError {
+    #[error("No IDs left to hand out")]
+    Exhausted,
+
+    #[error("Missing [id_generator.{0}] config for the selected backend")]
+    MissingConfig(&'static str),
+
+    #[error("Backend Error: {0}")]
+    BackendError(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// How many IDs a generator can still hand out.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Builder)]
+pub struct IdCapacity {
+    /// IDs reserved by this generator and not handed out yet
+    pub leased: u64,
+    /// IDs left in the range this generator draws from, including the leased ones
+    pub remaining: u64,
+    /// Set while the generator has run out of IDs, until it finds more
+    pub exhausted: bool,
+}
+
 pub trait IdGenerator: Send + Sync {
     fn generate_id(&self) -> impl Future<Output = Result<String, ShortenServiceError>> + Send;
+
+    fn capacity(&self) -> impl Future<Output = IdCapacity> + Send;
+
+    /// Whether the generator has lost its backing store for now; it may still
+    /// hand out IDs it already holds.
+    fn is_degraded(&self) -> bool {
+        false
+    }
 }
@@ -6,20 +6,24 @@ use std::future::Future;
 use std::sync::Arc;
 
 use cache::ShortenServiceCache;
-use chrono::NaiveDate;
+use chrono::{DateTime, Utc};
 use error::ShortenServiceError;
-use id_generator::IdGenerator;
-use tap::Pipe;
+use id_generator::{IdCapacity, IdGenerator, IdGeneratorError};
 use tracing::debug;
 use wee_core::domain::entities::url::Url;
-use wee_core::domain::repos::url_repo::UrlRepo;
+use wee_core::domain::repos::url_query::UrlQuery;
+use wee_core::domain::repos::url_repo::{GetUrlError, InsertUrlError, UrlRepo, UrlRepoError};
+use wee_core::domain::short_code::{CodeStyle, ShortCodeEncoder};
 
 #[derive(Debug, Clone, Builder)]
 pub struct ShortenParams {
     pub url: String,
     pub user_id: String,
     pub alias: Option<String>,
-    pub expiration_date: Option<NaiveDate>,
+    pub expiration_date: Option<DateTime<Utc>>,
+    pub fallback_url: Option<String>,
+    #[builder(default)]
+    pub code_style: CodeStyle,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Builder)]
@@ -29,7 +33,7 @@ pub struct ShortenResult {
     #[builder(required, into)]
     pub alias: Option<String>,
     #[builder(required, into)]
-    pub expiration_date: Option<NaiveDate>,
+    pub expiration_date: Option<DateTime<Utc>>,
 }
 
 pub trait ShortenServiceTrait: Send + Sync {
@@ -37,6 +41,10 @@ pub trait ShortenServiceTrait: Send + Sync {
         &self,
         params: ShortenParams,
     ) -> impl Future<Output = Result<ShortenResult, ShortenServiceError>> + Send;
+
+    fn id_capacity(&self) -> impl Future<Output = IdCapacity> + Send;
+
+    fn is_degraded(&self) -> bool;
 }
 
 #[derive(Debug, Clone)]
@@ -44,6 +52,7 @@ pub struct ShortenService<G: IdGenerator, R: UrlRepo, C: ShortenServiceCache> {
     pub id_generator: Arc<G>,
     pub repository: Arc<R>,
     pub cache: Arc<C>,
+    pub short_code: ShortCodeEncoder,
 }
 
 impl<G: IdGenerator, R: UrlRepo, C: ShortenServiceCache> ShortenServiceTrait
@@ -51,7 +60,15 @@ impl<G: IdGenerator, R: UrlRepo, C: ShortenServiceCache> ShortenServiceTrait
 {
     #[instrument(skip(self))]
     async fn shorten(&self, params: ShortenParams) -> Result<ShortenResult, ShortenServiceError> {
+        // Hashed codes don't draw an ID
+        if params.code_style != CodeStyle::Hashed && self.id_generator.capacity().await.exhausted {
+            return Err(IdGeneratorError::Exhausted.into());
+        }
+
         if let Some(alias) = params.alias.as_ref() {
+            if !self.short_code.can_be_alias(alias) {
+                return Err(ShortenServiceError::InvalidAlias(alias.clone()));
+            }
             if let Some(cached_url) = self.cache.get_by_alias(alias).await? {
                 return self.process_when_alias_was_cached(params, cached_url).await;
             }
@@ -76,14 +93,23 @@ impl<G: IdGenerator, R: UrlRepo, C: ShortenServiceCache> ShortenServiceTrait
             .expiration_date(url.expiration_date)
             .build())
     }
+
+    async fn id_capacity(&self) -> IdCapacity {
+        self.id_generator.capacity().await
+    }
+
+    fn is_degraded(&self) -> bool {
+        self.id_generator.is_degraded()
+    }
 }
 
 impl<G: IdGenerator, R: UrlRepo, C: ShortenServiceCache> ShortenService<G, R, C> {
-    pub fn new(id_generator: G, repository: R, cache: C) -> Self {
+    pub fn new(id_generator: G, repository: R, cache: C, short_code: ShortCodeEncoder) -> Self {
         ShortenService {
             id_generator: Arc::new(id_generator),
             repository: Arc::new(repository),
             cache: Arc::new(cache),
+            short_code,
         }
     }
 
@@ -91,33 +117,58 @@ impl<G: IdGenerator, R: UrlRepo, C: ShortenServiceCache> ShortenService<G, R, C>
         &self,
         shorten_params: ShortenParams,
     ) -> Result<Url, ShortenServiceError> {
-        let id_base62 = self
-            .id_generator
+        let short = match shorten_params.code_style {
+            CodeStyle::Compact => self.short_code.encode(self.next_id().await?),
+            CodeStyle::Words => self.short_code.encode_words(self.next_id().await?),
+            CodeStyle::Hashed => self.hashed_code(&shorten_params, 0)?,
+        };
+
+        Ok(Self::build_url(shorten_params, short))
+    }
+
+    async fn next_id(&self) -> Result<u64, ShortenServiceError> {
+        self.id_generator
             .generate_id()
             .await?
-            .pipe(|id| {
-                id.parse::<u128>()
-                    .map_err(|err| ShortenServiceError::InternalError(err.into()))
-            })?
-            .pipe(base62::encode);
+            .parse::<u64>()
+            .map_err(|err| ShortenServiceError::InternalError(err.into()))
+    }
 
-        let url = Url::builder()
+    fn hashed_code(
+        &self,
+        shorten_params: &ShortenParams,
+        attempt: usize,
+    ) -> Result<String, ShortenServiceError> {
+        self.short_code
+            .hashed(&shorten_params.url, &shorten_params.user_id, attempt)
+            .ok_or_else(|| {
+                ShortenServiceError::InternalError(
+                    format!("No hashed code left for {}", shorten_params.url).into(),
+                )
+            })
+    }
+
+    fn build_url(shorten_params: ShortenParams, short: String) -> Url {
+        Url::builder()
             .long(shorten_params.url)
-            .short(id_base62)
+            .short(short)
             .alias(shorten_params.alias)
             .expiration_date(shorten_params.expiration_date)
+            .maybe_fallback_url(shorten_params.fallback_url)
             .created_at(chrono::Utc::now().naive_utc())
             .updated_at(chrono::Utc::now().naive_utc())
             .user_id(shorten_params.user_id)
-            .build();
-
-        Ok(url)
+            .build()
     }
 
     pub async fn generate_and_save_url(
         &self,
         shorten_params: ShortenParams,
     ) -> Result<Url, ShortenServiceError> {
+        if shorten_params.code_style == CodeStyle::Hashed {
+            return self.generate_and_save_hashed_url(shorten_params).await;
+        }
+
         let url = self.generate_url(shorten_params).await?;
         self.repository.insert(url.clone()).await?;
         self.cache.cache(&url).await?;
@@ -125,6 +176,54 @@ impl<G: IdGenerator, R: UrlRepo, C: ShortenServiceCache> ShortenService<G, R, C>
         Ok(url)
     }
 
+    /// Saves a URL under its hashed code, lengthening the code while it collides with
+    /// another URL's. Shortening the same URL for the same user again, e.g. when
+    /// re-importing, returns the URL saved the first time.
+    pub async fn generate_and_save_hashed_url(
+        &self,
+        shorten_params: ShortenParams,
+    ) -> Result<Url, ShortenServiceError> {
+        let mut attempt = 0;
+
+        loop {
+            let short = self.hashed_code(&shorten_params, attempt)?;
+            let url = Self::build_url(shorten_params.clone(), short);
+
+            match self.repository.insert(url.clone()).await {
+                Ok(_) => {
+                    self.cache.cache(&url).await?;
+                    return Ok(url);
+                }
+                Err(UrlRepoError::Insert(InsertUrlError::AlreadyExists)) => {}
+                Err(err) => return Err(err.into()),
+            }
+
+            let existing = UrlQuery::ByUserAndLong {
+                user_id: url.user_id.clone(),
+                long: url.long.clone(),
+            };
+            if let Some(existing) = self.find(existing).await? {
+                return Ok(existing);
+            }
+            if let Some(alias) = url.alias.clone() {
+                if self.find(UrlQuery::ByAlias(alias.clone())).await?.is_some() {
+                    return Err(ShortenServiceError::AliasTaken(alias));
+                }
+            }
+
+            debug!("Hashed code {} is taken, lengthening it", url.short);
+            attempt += 1;
+        }
+    }
+
+    async fn find(&self, query: UrlQuery) -> Result<Option<Url>, ShortenServiceError> {
+        match self.repository.find(query).await {
+            Ok(url) => Ok(url),
+            Err(UrlRepoError::Get(GetUrlError::NotFound)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
     pub async fn process_when_alias_was_cached(
         &self,
         params: ShortenParams,
@@ -144,11 +243,11 @@ impl<G: IdGenerator, R: UrlRepo, C: ShortenServiceCache> ShortenService<G, R, C>
                 .expiration_date(url.expiration_date)
                 .build())
         } else if params.alias != cached_url.alias {
-            return Err(ShortenServiceError::UrlAlreadyExistedWithAlias(
+            Err(ShortenServiceError::UrlAlreadyExistedWithAlias(
                 cached_url.alias.unwrap(),
-            ));
-        } else if params.alias.is_some() && params.url != cached_url.long {
-            return Err(ShortenServiceError::AliasTaken(params.alias.unwrap()));
+            ))
+        } else if let Some(alias) = params.alias.filter(|_| params.url != cached_url.long) {
+            Err(ShortenServiceError::AliasTaken(alias))
         } else {
             debug!(
                 "Alias already exists with current user_id: {}",
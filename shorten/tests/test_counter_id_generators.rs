@@ -0,0 +1,116 @@
+mod utils;
+
+use std::env;
+
+use mongodb::bson::doc;
+use pretty_assertions::assert_eq;
+use redis::AsyncCommands;
+use utils::init_tracing;
+use wee_shorten::{
+    app_config::AppConfig,
+    outbound::{
+        id_generator::{AnyIdGenerator, IdGeneratorBackend},
+        mongodb::id_generator::{MongoIdGenerator, MongoIdGeneratorConfig, MongoIdGeneratorError},
+        redis::id_generator::{RedisIdGenerator, RedisIdGeneratorConfig, RedisIdGeneratorError},
+    },
+    services::shorten_service::id_generator::{IdGenerator, IdGeneratorError},
+};
+
+fn set_up(name: &str) -> AppConfig {
+    init_tracing();
+    env::set_var("RUN_MODE", "test");
+
+    let mut config = AppConfig::load();
+    let counter = format!(
+        "id-counter-test-{}-{}",
+        name,
+        chrono::Utc::now().timestamp()
+    );
+    config.id_generator.redis = Some(
+        RedisIdGeneratorConfig::builder()
+            .key(counter.clone())
+            .block_size(2)
+            .build(),
+    );
+    config.id_generator.mongodb = Some(
+        MongoIdGeneratorConfig::builder()
+            .collection("counters")
+            .counter(counter)
+            .block_size(2)
+            .build(),
+    );
+    config
+}
+
+#[tokio::test]
+async fn test_redis_id_generator() {
+    let config = set_up("redis");
+    let redis = config.id_generator.redis.clone().unwrap();
+    let first = RedisIdGenerator::new(config.redis.clone(), redis.clone())
+        .await
+        .unwrap();
+    let second = RedisIdGenerator::new(config.redis.clone(), redis.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(first.generate_id().await.unwrap(), "0");
+    assert_eq!(second.generate_id().await.unwrap(), "2");
+    assert_eq!(first.generate_id().await.unwrap(), "1");
+    assert_eq!(first.generate_id().await.unwrap(), "4");
+    assert_eq!(first.capacity().await.leased, 1);
+
+    let _: () = first.conn.clone().del(&redis.key).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_mongo_id_generator() {
+    let config = set_up("mongodb");
+    let mongodb = config.id_generator.mongodb.clone().unwrap();
+    let first = MongoIdGenerator::new(config.mongodb.clone(), mongodb.clone())
+        .await
+        .unwrap();
+    let second = MongoIdGenerator::new(config.mongodb.clone(), mongodb.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(first.generate_id().await.unwrap(), "0");
+    assert_eq!(second.generate_id().await.unwrap(), "2");
+    assert_eq!(first.generate_id().await.unwrap(), "1");
+    assert_eq!(first.generate_id().await.unwrap(), "4");
+
+    first
+        .collection
+        .delete_one(doc! {"_id": &mongodb.counter})
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_reject_zero_block_size() {
+    let config = set_up("zero-block-size");
+    let mut redis = config.id_generator.redis.clone().unwrap();
+    redis.block_size = 0;
+    let mut mongodb = config.id_generator.mongodb.clone().unwrap();
+    mongodb.block_size = 0;
+
+    assert!(matches!(
+        RedisIdGenerator::new(config.redis.clone(), redis).await,
+        Err(RedisIdGeneratorError::InvalidBlockSize)
+    ));
+    assert!(matches!(
+        MongoIdGenerator::new(config.mongodb.clone(), mongodb).await,
+        Err(MongoIdGeneratorError::InvalidBlockSize)
+    ));
+}
+
+#[tokio::test]
+async fn test_require_selected_backend_config() {
+    let mut config = set_up("missing-config");
+    config.id_generator.backend = IdGeneratorBackend::Redis;
+    config.id_generator.redis = None;
+
+    assert!(matches!(
+        AnyIdGenerator::new(&config).await,
+        Err(IdGeneratorError::MissingConfig("redis"))
+    ));
+}
@@ -0,0 +1,110 @@
+mod utils;
+
+use std::{
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{Duration, Utc};
+use pretty_assertions::assert_eq;
+use utils::init_tracing;
+use wee_core::{
+    domain::{entities::url::Url, repos::url_repo::UrlRepo},
+    outbound::in_memory::url_repo::InMemoryUrlRepo,
+    test_utils::url,
+};
+use wee_shorten::services::{
+    expiration_sweeper::{ExpirationSweeper, ExpirationSweeperConfig},
+    shorten_service::{cache::ShortenServiceCache, error::ShortenServiceError},
+};
+
+#[derive(Default)]
+struct RecordingCache {
+    evicted: Mutex<Vec<String>>,
+    /// Short code whose next eviction fails
+    failing: Mutex<Option<String>>,
+}
+
+impl ShortenServiceCache for RecordingCache {
+    async fn get_by_long_url(
+        &self,
+        _long_url: &str,
+        _user_id: &str,
+    ) -> Result<Option<Url>, ShortenServiceError> {
+        Ok(None)
+    }
+
+    async fn get_by_alias(&self, _alias: &str) -> Result<Option<Url>, ShortenServiceError> {
+        Ok(None)
+    }
+
+    async fn cache(&self, _url: &Url) -> Result<(), ShortenServiceError> {
+        Ok(())
+    }
+
+    async fn evict(&self, url: &Url) -> Result<(), ShortenServiceError> {
+        let mut failing = self.failing.lock().unwrap();
+        if failing.as_ref() == Some(&url.short) {
+            failing.take();
+            return Err(ShortenServiceError::InternalError("cache is down".into()));
+        }
+        self.evicted.lock().unwrap().push(url.short.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_sweep() {
+    init_tracing();
+    let repository = Arc::new(InMemoryUrlRepo::default());
+    let cache = Arc::new(RecordingCache::default());
+
+    for (short, days_left) in [
+        ("a", Some(-2)),
+        ("b", Some(-1)),
+        ("c", Some(1)),
+        ("d", None),
+    ] {
+        repository
+            .insert(
+                url(short)
+                    .alias(format!("alias-{}", short))
+                    .maybe_expiration_date(days_left.map(|days| Utc::now() + Duration::days(days)))
+                    .call(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let sweeper = ExpirationSweeper::new(
+        ExpirationSweeperConfig::builder()
+            .enabled(true)
+            .interval_secs(60)
+            .batch_size(NonZeroU32::MIN)
+            .build(),
+        repository.clone(),
+        cache.clone(),
+    );
+
+    cache.failing.lock().unwrap().replace("a".to_string());
+    assert_eq!(sweeper.sweep().await.unwrap(), 2);
+    assert_eq!(*cache.evicted.lock().unwrap(), vec!["b"]);
+    assert_eq!(repository.count().await.unwrap(), 2);
+
+    // The eviction that failed is retried on the next sweep
+    assert_eq!(sweeper.sweep().await.unwrap(), 0);
+    assert_eq!(*cache.evicted.lock().unwrap(), vec!["b", "a"]);
+}
+
+#[test]
+fn test_reject_zero_batch_size() {
+    let config = config::Config::builder()
+        .add_source(config::File::from_str(
+            "enabled = true\ninterval_secs = 60\nbatch_size = 0",
+            config::FileFormat::Toml,
+        ))
+        .build()
+        .unwrap();
+
+    assert!(config.try_deserialize::<ExpirationSweeperConfig>().is_err());
+}
@@ -0,0 +1,173 @@
+mod utils;
+
+use chrono::Utc;
+use pretty_assertions::assert_eq;
+use utils::init_tracing;
+use wee_core::{
+    domain::{
+        entities::url::Url,
+        repos::url_repo::UrlRepo,
+        short_code::{CodeStyle, ShortCodeConfig, ShortCodeEncoder},
+    },
+    outbound::in_memory::url_repo::InMemoryUrlRepo,
+};
+use wee_shorten::services::shorten_service::{
+    cache::ShortenServiceCache,
+    error::ShortenServiceError,
+    id_generator::{IdCapacity, IdGenerator, IdGeneratorError},
+    ShortenParams, ShortenService, ShortenServiceTrait,
+};
+
+/// Hashed codes must never draw an ID.
+struct NoIdGenerator;
+
+impl IdGenerator for NoIdGenerator {
+    async fn generate_id(&self) -> Result<String, ShortenServiceError> {
+        Err(IdGeneratorError::Exhausted.into())
+    }
+
+    async fn capacity(&self) -> IdCapacity {
+        IdCapacity::builder()
+            .leased(0)
+            .remaining(0)
+            .exhausted(true)
+            .build()
+    }
+}
+
+struct NoCache;
+
+impl ShortenServiceCache for NoCache {
+    async fn get_by_long_url(
+        &self,
+        _long_url: &str,
+        _user_id: &str,
+    ) -> Result<Option<Url>, ShortenServiceError> {
+        Ok(None)
+    }
+
+    async fn get_by_alias(&self, _alias: &str) -> Result<Option<Url>, ShortenServiceError> {
+        Ok(None)
+    }
+
+    async fn cache(&self, _url: &Url) -> Result<(), ShortenServiceError> {
+        Ok(())
+    }
+
+    async fn evict(&self, _url: &Url) -> Result<(), ShortenServiceError> {
+        Ok(())
+    }
+}
+
+fn set_up() -> ShortenService<NoIdGenerator, InMemoryUrlRepo, NoCache> {
+    init_tracing();
+
+    ShortenService::new(
+        NoIdGenerator,
+        InMemoryUrlRepo::default(),
+        NoCache,
+        ShortCodeEncoder::new(ShortCodeConfig::builder().key(42).build()).unwrap(),
+    )
+}
+
+fn params(url: &str, user_id: &str) -> ShortenParams {
+    ShortenParams::builder()
+        .url(url.to_string())
+        .user_id(user_id.to_string())
+        .code_style(CodeStyle::Hashed)
+        .build()
+}
+
+#[tokio::test]
+async fn test_hashed_codes_are_reproducible() {
+    let first = set_up();
+    let second = set_up();
+
+    let short = first
+        .shorten(params("https://example.com/a", "user"))
+        .await
+        .unwrap()
+        .short;
+    assert_eq!(
+        Some(short.clone()),
+        first.short_code.hashed("https://example.com/a", "user", 0)
+    );
+
+    // Another environment, and a re-import into the same one
+    assert_eq!(
+        second
+            .shorten(params("https://EXAMPLE.com/a", "user"))
+            .await
+            .unwrap()
+            .short,
+        short
+    );
+    assert_eq!(
+        first
+            .shorten(params("https://example.com/a", "user"))
+            .await
+            .unwrap()
+            .short,
+        short
+    );
+}
+
+#[tokio::test]
+async fn test_hashed_code_collision() {
+    let service = set_up();
+    let taken = service
+        .short_code
+        .hashed("https://example.com/a", "user", 0)
+        .unwrap();
+    service
+        .repository
+        .insert(
+            Url::builder()
+                .long("https://example.com/other".to_string())
+                .short(taken.clone())
+                .alias(None)
+                .expiration_date(None)
+                .user_id("someone".to_string())
+                .created_at(Utc::now().naive_utc())
+                .updated_at(Utc::now().naive_utc())
+                .build(),
+        )
+        .await
+        .unwrap();
+
+    let short = service
+        .shorten(params("https://example.com/a", "user"))
+        .await
+        .unwrap()
+        .short;
+
+    assert_eq!(short.len(), taken.len() + 1);
+    assert!(short.starts_with(&taken));
+}
+
+#[tokio::test]
+async fn test_strict_aliases() {
+    let mut service = set_up();
+    service.short_code =
+        ShortCodeEncoder::new(ShortCodeConfig::builder().key(42).strict(true).build()).unwrap();
+
+    // Made only of alphabet characters, it would look like a malformed short code
+    assert!(matches!(
+        service
+            .shorten(ShortenParams {
+                alias: Some("promo".to_string()),
+                ..params("https://example.com/promo", "test_user")
+            })
+            .await,
+        Err(ShortenServiceError::InvalidAlias(alias)) if alias == "promo"
+    ));
+
+    let result = service
+        .shorten(ShortenParams {
+            alias: Some("promo-2030".to_string()),
+            ..params("https://example.com/promo", "test_user")
+        })
+        .await
+        .unwrap();
+    assert_eq!(result.alias.as_deref(), Some("promo-2030"));
+}
@@ -16,9 +16,9 @@ fn set_up() {
 }
 
 async fn tear_down(cache: &mut RedisShortenServiceCache) {
-    let _: () = cache.conn.lock().await.flushdb().await.unwrap();
+    let _: () = cache.url_cache.conn.clone().flushdb().await.unwrap();
     // assert that the cache is empty
-    let keys: Vec<String> = cache.conn.lock().await.keys("*").await.unwrap();
+    let keys: Vec<String> = cache.url_cache.conn.clone().keys("*").await.unwrap();
     assert!(keys.is_empty());
 }
 
@@ -60,5 +60,71 @@ async fn test_cache_and_get_by_long_url() {
         .unwrap();
     assert!(non_existent.is_none());
 
+    // Test evicting the cached URL
+    cache.evict(&url).await.unwrap();
+    let evicted = cache
+        .get_by_long_url("https://example.com", "test_user")
+        .await
+        .unwrap();
+    assert!(evicted.is_none());
+    assert!(cache.get_by_alias("abc123").await.unwrap().is_none());
+
+    tear_down(&mut cache).await;
+}
+
+#[tokio::test]
+async fn test_cache_ttl() {
+    set_up();
+    let config = AppConfig::load();
+    let mut cache = RedisShortenServiceCache::new(config.redis.clone())
+        .await
+        .unwrap();
+
+    let url = |short: &str, expires_in: Option<chrono::Duration>| {
+        Url::builder()
+            .long(format!("https://example.com/{}", short))
+            .short(short.to_string())
+            .alias(Some(format!("alias-{}", short)))
+            .expiration_date(expires_in.map(|duration| chrono::Utc::now() + duration))
+            .user_id("ttl_user".to_string())
+            .created_at(chrono::Utc::now().naive_utc())
+            .updated_at(chrono::Utc::now().naive_utc())
+            .build()
+    };
+
+    // Expires before the maximum TTL
+    let soon = url("soon", Some(chrono::Duration::minutes(10)));
+    cache.cache(&soon).await.unwrap();
+    // Never expires, so it is kept for the maximum TTL
+    let never = url("never", None);
+    cache.cache(&never).await.unwrap();
+    // Already expired, so it isn't cached at all
+    let expired = url("expired", Some(-chrono::Duration::minutes(1)));
+    cache.cache(&expired).await.unwrap();
+
+    let mut conn = cache.url_cache.conn.clone();
+    let keys = cache.url_cache.keys.clone();
+    for key in [keys.short("soon"), keys.alias("alias-soon")] {
+        let ttl: i64 = conn.ttl(&key).await.unwrap();
+        assert!((590..=600).contains(&ttl), "{} expires in {}s", key, ttl);
+    }
+    let ttl: i64 = conn.ttl(keys.short("never")).await.unwrap();
+    assert_eq!(ttl, config.redis.max_ttl_secs.get() as i64);
+
+    let ttls: Vec<i64> = conn
+        .httl(
+            keys.user_urls("ttl_user"),
+            &[&soon.long, &never.long, &expired.long],
+        )
+        .await
+        .unwrap();
+    assert!((590..=600).contains(&ttls[0]));
+    assert_eq!(ttls[1], config.redis.max_ttl_secs.get() as i64);
+    // -2: no such field
+    assert_eq!(ttls[2], -2);
+
+    let exists: bool = conn.exists(keys.short("expired")).await.unwrap();
+    assert!(!exists);
+
     tear_down(&mut cache).await;
 }
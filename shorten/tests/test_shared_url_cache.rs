@@ -0,0 +1,39 @@
+use pretty_assertions::assert_eq;
+use wee_core::outbound::redis::{RedisConfig, URL_CACHE_DB};
+
+fn redis_config(files: &[&str]) -> RedisConfig {
+    files
+        .iter()
+        .fold(config::Config::builder(), |builder, file| {
+            builder.add_source(config::File::with_name(file))
+        })
+        .build()
+        .unwrap()
+        .get::<RedisConfig>("redis")
+        .unwrap()
+}
+
+/// The redirect service reads the entries the shorten service writes, so both must
+/// agree on where they live.
+#[test]
+fn test_services_share_url_cache() {
+    for (shorten, redirect) in [
+        (vec!["configs/default"], vec!["../redirect/configs/default"]),
+        (
+            vec!["configs/default", "configs/test"],
+            vec!["../redirect/configs/default", "../redirect/configs/test"],
+        ),
+        (
+            vec!["../.docker/configs/shorten/default"],
+            vec!["../.docker/configs/redirect/default"],
+        ),
+    ] {
+        let shorten = redis_config(&shorten);
+        let redirect = redis_config(&redirect);
+
+        assert_eq!(shorten.host, redirect.host);
+        assert_eq!(shorten.port, redirect.port);
+        assert_eq!(shorten.namespace, redirect.namespace);
+        assert_eq!(shorten.dbs[URL_CACHE_DB], redirect.dbs[URL_CACHE_DB]);
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Duration, Utc};
+use pretty_assertions::assert_eq;
+use validator::Validate;
+use wee_core::domain::short_code::CodeStyle;
+use wee_shorten::inbound::rest::handlers::shorten::{parse_expiration_date, ShortenRequestPayload};
+
+fn now() -> DateTime<Utc> {
+    "2030-01-01T12:00:00Z".parse().unwrap()
+}
+
+#[test]
+fn test_parse_relative_expiration_date() {
+    for (value, duration) in [
+        ("30s", Duration::seconds(30)),
+        ("15m", Duration::minutes(15)),
+        ("12h", Duration::hours(12)),
+        ("7d", Duration::days(7)),
+        ("2w", Duration::weeks(2)),
+    ] {
+        assert_eq!(
+            parse_expiration_date(value, now()).unwrap(),
+            now() + duration
+        );
+    }
+
+    assert!(parse_expiration_date("7y", now()).is_err());
+    assert!(parse_expiration_date("-7d", now()).is_err());
+    assert!(parse_expiration_date("100000000d", now()).is_err());
+    assert!(parse_expiration_date("4294967295w", now()).is_err());
+}
+
+#[test]
+fn test_parse_absolute_expiration_date() {
+    assert_eq!(
+        parse_expiration_date("2030-02-01T09:30:00.250-05:00", now()).unwrap(),
+        "2030-02-01T14:30:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+    assert_eq!(
+        parse_expiration_date("2030-02-01", now()).unwrap(),
+        "2030-02-02T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+}
+
+#[test]
+fn test_payload_expiration_date() {
+    let payload = serde_json::from_value::<ShortenRequestPayload>(serde_json::json!({
+        "url": "https://example.com",
+        "userId": "test_user",
+        "expirationDate": "1h",
+    }))
+    .unwrap();
+    assert!(payload.expiration_date.unwrap() > Utc::now());
+    assert!(payload.validate().is_ok());
+
+    let payload = serde_json::from_value::<ShortenRequestPayload>(serde_json::json!({
+        "url": "https://example.com",
+        "userId": "test_user",
+    }))
+    .unwrap();
+    assert_eq!(payload.expiration_date, None);
+
+    let payload = serde_json::from_value::<ShortenRequestPayload>(serde_json::json!({
+        "url": "https://example.com",
+        "userId": "test_user",
+        "expirationDate": "2020-01-01T00:00:00Z",
+    }))
+    .unwrap();
+    assert!(payload.validate().is_err());
+
+    assert!(
+        serde_json::from_value::<ShortenRequestPayload>(serde_json::json!({
+            "url": "https://example.com",
+            "userId": "test_user",
+            "expirationDate": "soon",
+        }))
+        .is_err()
+    );
+}
+
+#[test]
+fn test_payload_code_style() {
+    let payload = serde_json::from_value::<ShortenRequestPayload>(serde_json::json!({
+        "url": "https://example.com",
+        "userId": "test_user",
+    }))
+    .unwrap();
+    assert_eq!(payload.code_style, CodeStyle::Compact);
+
+    let payload = serde_json::from_value::<ShortenRequestPayload>(serde_json::json!({
+        "url": "https://example.com",
+        "userId": "test_user",
+        "codeStyle": "words",
+    }))
+    .unwrap();
+    assert_eq!(payload.code_style, CodeStyle::Words);
+
+    assert!(
+        serde_json::from_value::<ShortenRequestPayload>(serde_json::json!({
+            "url": "https://example.com",
+            "userId": "test_user",
+            "codeStyle": "emoji",
+        }))
+        .is_err()
+    );
+}
@@ -0,0 +1,53 @@
+use std::collections::HashSet;
+
+use pretty_assertions::assert_eq;
+use wee_shorten::{
+    outbound::snowflake::id_generator::{
+        SnowflakeIdGenerator, SnowflakeIdGeneratorConfig, SnowflakeIdGeneratorError,
+    },
+    services::shorten_service::id_generator::IdGenerator,
+};
+
+fn config(worker_id: u16) -> SnowflakeIdGeneratorConfig {
+    SnowflakeIdGeneratorConfig::builder()
+        .worker_id(worker_id)
+        .epoch_ms(1_735_689_600_000)
+        .build()
+}
+
+#[tokio::test]
+async fn test_generate_unique_increasing_ids() {
+    let generator = SnowflakeIdGenerator::new(config(7)).unwrap();
+
+    let mut ids = Vec::new();
+    for _ in 0..10_000 {
+        ids.push(generator.next_id().unwrap());
+    }
+
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(ids.iter().all(|id| (id >> 12) & 0x3ff == 7));
+
+    let id = generator.generate_id().await.unwrap();
+    assert!(id.parse::<u64>().unwrap() > *ids.last().unwrap());
+}
+
+#[tokio::test]
+async fn test_workers_do_not_collide() {
+    let first = SnowflakeIdGenerator::new(config(1)).unwrap();
+    let second = SnowflakeIdGenerator::new(config(2)).unwrap();
+
+    let ids = (0..1_000)
+        .flat_map(|_| [first.next_id().unwrap(), second.next_id().unwrap()])
+        .collect::<HashSet<_>>();
+    assert_eq!(ids.len(), 2_000);
+
+    assert!(!first.capacity().await.exhausted);
+}
+
+#[test]
+fn test_invalid_worker_id() {
+    assert!(matches!(
+        SnowflakeIdGenerator::new(config(1_024)),
+        Err(SnowflakeIdGeneratorError::InvalidWorkerId(1_024))
+    ));
+}
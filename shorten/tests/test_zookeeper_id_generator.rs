@@ -1,26 +1,29 @@
 mod utils;
 
 use pretty_assertions::assert_eq;
-use std::env;
+use std::{env, net::ToSocketAddrs, sync::atomic::Ordering};
+use tokio_zookeeper::{Acl, CreateMode};
 use tracing::debug;
 
 use wee_shorten::{
-    app_config::AppConfig, outbound::zookeeper::id_generator::ZooKeeperIdGenerator,
-    services::shorten_service::id_generator::IdGenerator,
+    app_config::AppConfig,
+    outbound::zookeeper::id_generator::ZooKeeperIdGenerator,
+    services::shorten_service::{
+        error::ShortenServiceError,
+        id_generator::{IdGenerator, IdGeneratorError},
+    },
 };
 
 use utils::init_tracing;
 
-async fn set_up() -> i64 {
+async fn set_up(name: &str) -> AppConfig {
     init_tracing();
     env::set_var("RUN_MODE", "test");
     let current_ts = chrono::Utc::now().timestamp();
-    env::set_var(
-        "SHORTEN__ZOOKEEPER__ID_GENERATOR__SHARD_INFO__BASE_PATH",
-        format!("wee-test-{}", current_ts),
-    );
 
-    current_ts
+    let mut config = AppConfig::load();
+    config.zookeeper.id_generator.base_path = format!("wee-test-{}-{}", current_ts, name);
+    config
 }
 
 fn delete_recursive<'a>(
@@ -28,7 +31,7 @@ fn delete_recursive<'a>(
     path: &'a str,
 ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + 'a>> {
     Box::pin(async move {
-        let children = zk.client.get_children(path).await.unwrap();
+        let children = zk.session.client().await.get_children(path).await.unwrap();
 
         if let Some(children) = children {
             for child in children {
@@ -37,23 +40,268 @@ fn delete_recursive<'a>(
             }
         }
 
-        zk.client.delete(path, None).await.unwrap().unwrap();
+        zk.session
+            .client()
+            .await
+            .delete(path, None)
+            .await
+            .unwrap()
+            .unwrap();
         debug!("Deleted path: {}", path);
     })
 }
 
-async fn tear_down(ts: i64, zk: &ZooKeeperIdGenerator) {
-    delete_recursive(zk, &format!("/wee-test-{}", ts)).await;
+async fn tear_down(config: &AppConfig, zk: &ZooKeeperIdGenerator) {
+    delete_recursive(zk, &format!("/{}", config.zookeeper.id_generator.base_path)).await;
 }
 
 #[tokio::test]
 async fn test_generate_id() {
-    let ts = set_up().await;
-    let config = AppConfig::load();
+    let config = set_up("generate-id").await;
     let zk = ZooKeeperIdGenerator::new(config.zookeeper.clone())
         .await
         .unwrap();
     let id = zk.generate_id().await.unwrap();
     assert_eq!(id.parse::<i64>().unwrap(), 0);
-    tear_down(ts, &zk).await;
+    tear_down(&config, &zk).await;
+}
+
+#[tokio::test]
+async fn test_lease_blocks() {
+    let config = set_up("lease-blocks").await;
+    let block_size = config.zookeeper.id_generator.block_size;
+    let zk = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+
+    for expected in 0..3 {
+        let id = zk.generate_id().await.unwrap();
+        assert_eq!(id.parse::<usize>().unwrap(), expected);
+    }
+
+    let shard_info = config.zookeeper.id_generator.shard_info(0);
+    let leases = zk
+        .session
+        .client()
+        .await
+        .get_children(&shard_info.leases_path())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(leases, vec![format!("lease-0-{}", block_size - 1)]);
+
+    tear_down(&config, &zk).await;
+}
+
+#[tokio::test]
+async fn test_claim_shards() {
+    let config = set_up("claim-shards").await;
+    let shard_size = config.zookeeper.id_generator.shard_size;
+    let first = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+    let second = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(first.lease.lock().await.shard_info.id, 0);
+    assert_eq!(second.lease.lock().await.shard_info.id, 1);
+    let id = second.generate_id().await.unwrap();
+    assert_eq!(id.parse::<usize>().unwrap(), shard_size);
+
+    // A released shard can be claimed again
+    first.release().await.unwrap();
+    let third = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+    assert_eq!(third.lease.lock().await.shard_info.id, 0);
+
+    third.release().await.unwrap();
+    second.release().await.unwrap();
+    tear_down(&config, &first).await;
+}
+
+#[tokio::test]
+async fn test_release_used_blocks() {
+    let mut config = set_up("release-used-blocks").await;
+    config.zookeeper.id_generator.block_size = 2;
+    let zk = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+
+    // Rolls over to the second block
+    for expected in 0..3 {
+        let id = zk.generate_id().await.unwrap();
+        assert_eq!(id.parse::<usize>().unwrap(), expected);
+    }
+
+    let shard_info = config.zookeeper.id_generator.shard_info(0);
+    let leases = zk
+        .session
+        .client()
+        .await
+        .get_children(&shard_info.leases_path())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(leases, vec!["lease-2-3".to_string()]);
+
+    tear_down(&config, &zk).await;
+}
+
+#[tokio::test]
+async fn test_continue_after_legacy_ids() {
+    let mut config = set_up("legacy-ids").await;
+    config.zookeeper.id_generator.shard_size = 2;
+    let address = (config.zookeeper.host.as_str(), config.zookeeper.port)
+        .to_socket_addrs()
+        .unwrap()
+        .next()
+        .unwrap();
+    let (client, _watcher) = tokio_zookeeper::ZooKeeper::connect(&address).await.unwrap();
+
+    // Earlier versions created one sequential node per ID under a static shard node
+    let base_path = format!("/{}", config.zookeeper.id_generator.base_path);
+    let legacy_path = format!("{}/shard-0-0-1000", base_path);
+    for path in [base_path, legacy_path.clone()] {
+        client
+            .create(&path, b"", Acl::open_unsafe(), CreateMode::Persistent)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+    for _ in 0..3 {
+        client
+            .create(
+                &format!("{}/", legacy_path),
+                b"",
+                Acl::open_unsafe(),
+                CreateMode::PersistentSequential,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    let zk = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+    // IDs 0 to 2 were handed out, which uses up shard 0 and the first ID of shard 1
+    assert_eq!(zk.lease.lock().await.shard_info.id, 1);
+    let id = zk.generate_id().await.unwrap();
+    assert_eq!(id.parse::<usize>().unwrap(), 3);
+
+    // Later claims skip the exhausted shard
+    let (exhausted, _) = zk
+        .session
+        .client()
+        .await
+        .get_data(&config.zookeeper.id_generator.exhausted_path())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(exhausted, b"1");
+
+    tear_down(&config, &zk).await;
+}
+
+#[tokio::test]
+async fn test_shard_exhausted() {
+    let mut config = set_up("shard-exhausted").await;
+    config.zookeeper.id_generator.shard_size = 2;
+    config.zookeeper.id_generator.shard_count = 2;
+    config.zookeeper.id_generator.block_size = 1;
+    let zk = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+
+    // Nobody has started shard 1 yet
+    assert_eq!(zk.capacity().await.remaining, 4);
+    let other = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(zk.generate_id().await.unwrap(), "0");
+    assert_eq!(zk.capacity().await.remaining, 1);
+    assert_eq!(zk.generate_id().await.unwrap(), "1");
+
+    assert!(matches!(
+        zk.generate_id().await,
+        Err(ShortenServiceError::IdGeneratorError(
+            IdGeneratorError::Exhausted
+        ))
+    ));
+    let capacity = zk.capacity().await;
+    assert!(capacity.exhausted);
+    assert_eq!(capacity.remaining, 0);
+
+    // The shard another replica releases is claimed on the next call
+    other.release().await.unwrap();
+    let capacity = zk.capacity().await;
+    assert!(!capacity.exhausted);
+    assert_eq!(capacity.remaining, 2);
+    assert_eq!(zk.generate_id().await.unwrap(), "2");
+
+    tear_down(&config, &zk).await;
+}
+
+#[tokio::test]
+async fn test_restore_ownership_after_new_session() {
+    let config = set_up("restore-ownership").await;
+    let zk = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+    let owner_path = config.zookeeper.id_generator.shard_info(0).owner_path();
+    let client = zk.session.client().await;
+
+    // An expired session takes its ephemeral owner node with it
+    client.delete(&owner_path, None).await.unwrap().unwrap();
+    zk.session.renewed.store(true, Ordering::SeqCst);
+
+    assert_eq!(zk.generate_id().await.unwrap(), "0");
+    assert!(client.exists(&owner_path).await.unwrap().is_some());
+    assert!(zk.lease.lock().await.owned);
+    assert!(!zk.is_degraded());
+
+    tear_down(&config, &zk).await;
+}
+
+#[tokio::test]
+async fn test_restore_ownership_from_old_session() {
+    let config = set_up("restore-old-session").await;
+    let zk = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+
+    // The owner node the old session still holds is ours, not another replica's
+    zk.session.renewed.store(true, Ordering::SeqCst);
+
+    assert_eq!(zk.generate_id().await.unwrap(), "0");
+    assert!(zk.lease.lock().await.owned);
+
+    tear_down(&config, &zk).await;
+}
+
+#[tokio::test]
+async fn test_release_keeps_other_owner() {
+    let config = set_up("release-other-owner").await;
+    let zk = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+    let owner_path = config.zookeeper.id_generator.shard_info(0).owner_path();
+    let client = zk.session.client().await;
+
+    // Another replica claims the shard after our owner node expired
+    client.delete(&owner_path, None).await.unwrap().unwrap();
+    let other = ZooKeeperIdGenerator::new(config.zookeeper.clone())
+        .await
+        .unwrap();
+    assert_eq!(other.lease.lock().await.shard_info.id, 0);
+
+    zk.release().await.unwrap();
+    let stat = client.exists(&owner_path).await.unwrap().unwrap();
+    assert_eq!(stat.ephemeral_owner, other.lease.lock().await.owner_session);
+
+    other.release().await.unwrap();
+    tear_down(&config, &zk).await;
 }